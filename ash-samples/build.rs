@@ -0,0 +1,163 @@
+// Compiles the shaders in `shaders/` to SPIR-V with naga, so neither the samples nor whoever builds
+// them needs an external shader compiler.
+//
+// `name.vert`, `name.frag` and `name.comp` are GLSL; `name.wgsl` is WGSL, which declares its stages
+// itself. Each becomes `$OUT_DIR/<file name>.spv`, and `$OUT_DIR/shaders.rs` gets a constant with
+// its bytes, named after the file: `draw_cube.vert` is `shaders::DRAW_CUBE_VERT`. Errors are
+// reported as `path:line:column: message`, the way compilers usually do.
+
+extern crate naga;
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use naga::back::spv;
+use naga::front::{glsl, wgsl};
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+const SHADER_DIR: &str = "shaders";
+
+#[derive(Clone, Copy)]
+enum Language {
+    Glsl(naga::ShaderStage),
+    Wgsl,
+}
+
+fn language(path: &Path) -> Option<Language> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("vert") => Some(Language::Glsl(naga::ShaderStage::Vertex)),
+        Some("frag") => Some(Language::Glsl(naga::ShaderStage::Fragment)),
+        Some("comp") => Some(Language::Glsl(naga::ShaderStage::Compute)),
+        Some("wgsl") => Some(Language::Wgsl),
+        _ => None,
+    }
+}
+
+// `message`, prefixed with where in `path` it applies, if known
+fn located(path: &Path, location: Option<naga::SourceLocation>, message: &str) -> String {
+    match location {
+        Some(location) => format!(
+            "{}:{}:{}: {}",
+            path.display(),
+            location.line_number,
+            location.line_position,
+            message
+        ),
+        None => format!("{}: {}", path.display(), message),
+    }
+}
+
+// `error` followed by the errors that caused it, as naga's errors only say what went wrong at
+// their own level
+fn with_causes(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut cause = error.source();
+    while let Some(error) = cause {
+        message.push_str(": ");
+        message.push_str(&error.to_string());
+        cause = error.source();
+    }
+    message
+}
+
+fn compile(path: &Path, language: Language) -> Result<Vec<u32>, Vec<String>> {
+    let source = fs::read_to_string(path)
+        .map_err(|error| vec![located(path, None, &error.to_string())])?;
+
+    let module = match language {
+        Language::Glsl(stage) => glsl::Frontend::default()
+            .parse(&glsl::Options::from(stage), &source)
+            .map_err(|errors| {
+                errors
+                    .iter()
+                    .map(|error| {
+                        let location = error.meta.location(&source);
+                        located(path, Some(location), &error.kind.to_string())
+                    })
+                    .collect::<Vec<_>>()
+            })?,
+        Language::Wgsl => wgsl::parse_str(&source)
+            .map_err(|error| vec![located(path, error.location(&source), error.message())])?,
+    };
+
+    let info = Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .map_err(|error| {
+            let location = error.location(&source);
+            vec![located(path, location, &with_causes(&error.into_inner()))]
+        })?;
+
+    let mut options = spv::Options::default();
+    // naga flips y for WGSL, whose clip space points up; GLSL written for Vulkan already has it
+    // pointing down, and `Camera::mvp` accounts for that
+    if let Language::Glsl(_) = language {
+        options.flags.remove(spv::WriterFlags::ADJUST_COORDINATE_SPACE);
+    }
+    spv::write_vec(&module, &info, &options, None)
+        .map_err(|error| vec![located(path, None, &with_causes(&error))])
+}
+
+// `draw_cube.vert` → `DRAW_CUBE_VERT`
+fn constant_name(file_name: &str) -> String {
+    file_name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect()
+}
+
+fn main() {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    println!("cargo:rerun-if-changed={}", SHADER_DIR);
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(SHADER_DIR)
+        .unwrap_or_else(|error| panic!("failed to read {}: {}", SHADER_DIR, error))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| language(path).is_some())
+        .collect();
+    // keeps the generated constants in a stable order
+    paths.sort();
+
+    let mut constants = String::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+
+        let words = match compile(&path, language(&path).unwrap()) {
+            Ok(words) => words,
+            Err(mut compile_errors) => {
+                errors.append(&mut compile_errors);
+                continue;
+            }
+        };
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect();
+        let spv_path = out_dir.join(format!("{}.spv", file_name));
+        fs::write(&spv_path, bytes)
+            .unwrap_or_else(|error| panic!("failed to write {}: {}", spv_path.display(), error));
+
+        constants.push_str(&format!(
+            "// SPIR-V compiled from shaders/{}\npub const {}: &[u8] = include_bytes!({:?});\n",
+            file_name,
+            constant_name(&file_name),
+            spv_path.display().to_string()
+        ));
+    }
+
+    if !errors.is_empty() {
+        for error in errors.iter() {
+            eprintln!("{}", error);
+        }
+        eprintln!("{} shader compilation error(s)", errors.len());
+        process::exit(1);
+    }
+
+    let constants_path = out_dir.join("shaders.rs");
+    fs::write(&constants_path, constants)
+        .unwrap_or_else(|error| panic!("failed to write {}: {}", constants_path.display(), error));
+}
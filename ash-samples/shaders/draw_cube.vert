@@ -0,0 +1,16 @@
+#version 450
+
+// the MVP matrix from the uniform buffer, see `Camera::mvp`
+layout(set = 0, binding = 0) uniform Uniforms {
+    mat4 mvp;
+} uniforms;
+
+layout(location = 0) in vec4 pos;
+layout(location = 1) in vec4 in_color;
+
+layout(location = 0) out vec4 color;
+
+void main() {
+    color = in_color;
+    gl_Position = uniforms.mvp * pos;
+}
@@ -0,0 +1,84 @@
+extern crate ash_samples;
+#[macro_use]
+extern crate serde_json;
+extern crate log;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+
+// Dumps everything each physical device reports about itself, vulkaninfo-style. Pass `--json` to
+// get a single JSON document, e.g. for attaching to bug reports or diffing across drivers, and
+// `--output <path>` to write the report to a file instead of stdout. Logging goes to stderr, so the
+// report can be piped straight into other tools.
+fn main() {
+    ash_samples::init_logger(log::LevelFilter::Info);
+
+    let mut as_json = false;
+    let mut output_path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--json" {
+            as_json = true;
+        } else if arg == "--output" {
+            output_path = args.next();
+        } else if arg.starts_with("--output=") {
+            output_path = Some(String::from(&arg["--output=".len()..]));
+        }
+    }
+
+    let validation_tally = unsafe {
+        let builder = ash_samples::InstanceBuilder::new("device-report-sample");
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
+
+        let pdevices = match ash_samples::enumerate_pdevices(&instance) {
+            Ok(pdevices) => pdevices,
+            Err(error) => panic!("{}", error),
+        };
+
+        let mut reports = Vec::new();
+        for &pdevice in pdevices.iter() {
+            match ash_samples::pdevice_report(&instance, pdevice) {
+                Ok(report) => reports.push(report),
+                Err(error) => panic!("{}", error),
+            }
+        }
+
+        let output = match as_json {
+            true => {
+                let document = json!({ "pdevices": reports });
+                serde_json::to_string_pretty(&document).unwrap() + "\n"
+            }
+            false => reports
+                .iter()
+                .enumerate()
+                .map(|(index, report)| {
+                    format!(
+                        "========= pdevice {} =========\n{}",
+                        index,
+                        ash_samples::format_report(report)
+                    )
+                })
+                .collect(),
+        };
+
+        match output_path {
+            Some(path) => {
+                let written = File::create(&path).and_then(|mut file| file.write_all(output.as_bytes()));
+                match written {
+                    Ok(()) => println!("Report written to {}.", path),
+                    Err(error) => panic!("failed to write report to {}: {}", path, error),
+                }
+            }
+            None => print!("{}", output),
+        }
+
+        debug_callback.as_ref().map(|callback| callback.tally())
+    };
+
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
+}
@@ -0,0 +1,56 @@
+extern crate ash_samples;
+extern crate ash;
+
+use ash::Entry;
+use ash::version::V1_0;
+
+// code is a Rust version of: https://github.com/LunarG/VulkanSamples/blob/master/API-Samples/instance_layer_extension_properties/instance_layer_extension_properties.cpp
+// please look at ash-tutorial.pdf for further information!
+fn main() {
+    // layers and extensions can be queried before any instance exists
+    let entry: Entry<V1_0> = match Entry::new() {
+        Ok(entry) => entry,
+        Err(error) => panic!("{}", ash_samples::Error::from(error)),
+    };
+
+    let extensions = match ash_samples::enumerate_instance_extensions(&entry, None) {
+        Ok(extensions) => extensions,
+        Err(error) => panic!("{}", error),
+    };
+
+    println!("{} instance extensions found:", extensions.len());
+    for extension in extensions.iter() {
+        println!(
+            "    {} (version {})",
+            extension.name, extension.spec_version
+        );
+    }
+
+    let layers = match ash_samples::enumerate_instance_layers(&entry) {
+        Ok(layers) => layers,
+        Err(error) => panic!("{}", error),
+    };
+
+    println!("{} instance layers found:", layers.len());
+    for layer in layers.iter() {
+        println!("=========");
+        print!(
+            "name: {}\n\
+             description: {}\n\
+             spec version: {}\n\
+             implementation version: {}\n\
+             extensions:\n",
+            layer.name,
+            layer.description,
+            ash_samples::version_string(layer.spec_version),
+            layer.implementation_version
+        );
+        for extension in layer.extensions.iter() {
+            println!(
+                "    {} (version {})",
+                extension.name, extension.spec_version
+            );
+        }
+    }
+    println!("=========");
+}
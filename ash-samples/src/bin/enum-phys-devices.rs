@@ -1,29 +1,31 @@
 extern crate ash_samples;
 extern crate ash;
+extern crate log;
 
-use ash::Entry;
-use ash::Instance;
-use ash::version::{InstanceV1_0, V1_0};
+use ash::version::InstanceV1_0;
 
 // please look at ash-tutorial.pdf for further information!
 fn main() {
-    unsafe {
-        let (_entry, instance): (Entry<V1_0>, Instance<V1_0>) = ash_samples::init_instance_without_extensions("enumerate-devices-sample");
+    ash_samples::init_logger(log::LevelFilter::Info);
+
+    let validation_tally = unsafe {
+        // the instance is destroyed when `instance` goes out of scope, even if we panic below
+        let builder = ash_samples::InstanceBuilder::new("enumerate-devices-sample");
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
 
         let pdevices = match instance.enumerate_physical_devices() {
             Ok(pdevices) => pdevices,
-            Err(error) => {
-                // we should destroy the instance we have created, before panicking
-                ash_samples::destroy_instance_and_panic(
-                    &format!("failed to create pdevices: {:?}", error),
-                    instance,
-                );
-            }
+            Err(error) => panic!("failed to create pdevices: {:?}", error),
         };
 
         println!("pdevices found: {}.", pdevices.len());
 
-        println!("Destroying instance...");
-        instance.destroy_instance(None);
-    }
-}
\ No newline at end of file
+        debug_callback.as_ref().map(|callback| callback.tally())
+    };
+
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
+}
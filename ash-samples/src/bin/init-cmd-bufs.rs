@@ -1,52 +1,54 @@
 extern crate ash;
 extern crate ash_samples;
+extern crate log;
 
 use ash::vk;
-use ash::Entry;
-use ash::Instance;
-use ash::Device;
-use ash::version::{DeviceV1_0, InstanceV1_0, V1_0};
+use ash::version::{DeviceV1_0, InstanceV1_0};
 use std::ptr;
 
 // code is a Rust version of: https://github.com/LunarG/VulkanSamples/blob/master/API-Samples/02-enumerate_devices/02-enumerate_devices.cpp
 // please look at ash-tutorial.pdf for further information!
 
 fn main() {
-    unsafe {
-        let (_entry, instance): (Entry<V1_0>, Instance<V1_0>) =
-            ash_samples::init_instance_without_extensions("init-command-buffer-sample");
+    ash_samples::init_logger(log::LevelFilter::Info);
 
-        let pdevices = match instance.enumerate_physical_devices() {
+    let validation_tally = unsafe {
+        let builder = ash_samples::InstanceBuilder::new("init-command-buffer-sample");
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
+
+        let pdevices = match ash_samples::enumerate_pdevices(&instance) {
             Ok(pdevices) => pdevices,
-            Err(error) => {
-                // we should destroy the instance we have created, before panicking
-                ash_samples::destroy_instance_and_panic(
-                    &format!("failed to get list of pdevices: {:?}", error),
-                    instance,
-                );
-            }
+            Err(error) => panic!("{}", error),
         };
 
         println!("{} pdevices found.", pdevices.len());
-        if pdevices.len() == 0 {
-            clean_up_and_panic("No physical devices found!", instance, None, Vec::<vk::types::CommandPool>::new());
-        }
+
+        let selector = match ash_samples::PdeviceSelector::from_args_or_env() {
+            Ok(selector) => selector,
+            Err(error) => panic!("{}", error),
+        };
+
+        let pdevices = match ash_samples::rank_pdevices(
+            &ash_samples::InstanceQuery::new(&instance),
+            pdevices,
+            &ash_samples::DefaultScorer::new(),
+            selector.as_ref(),
+        ) {
+            Ok(pdevices) => pdevices,
+            Err(error) => panic!("{}", error),
+        };
 
         let (pdevice, queue_family_index): (vk::types::PhysicalDevice, usize) =
             match ash_samples::find_relevant_pdevice_and_queue_family(
-                &instance,
+                &ash_samples::InstanceQuery::new(&instance),
                 pdevices,
                 vec![vk::QUEUE_GRAPHICS_BIT],
             ) {
-                Some(result) => result,
-                None => {
-                    clean_up_and_panic(
-                        "Could not find a capable physical device!",
-                        instance,
-                        None,
-                        Vec::<vk::types::CommandPool>::new(),
-                    );
-                }
+                Ok(result) => result,
+                Err(error) => panic!("{}", error),
             };
 
         let qfp_info =
@@ -86,20 +88,14 @@ fn main() {
             p_enabled_features: ptr::null(),
         };
 
-        let device: Device<V1_0> = match instance.create_device(pdevice, &device_create_info, None)
-        {
+        let device = match instance.create_device(pdevice, &device_create_info, None) {
             Ok(device) => {
                 println!("Successfully created logical device.");
-                device
-            }
-            Err(error) => {
-                clean_up_and_panic(
-                    &format!("failed to create logical device: {:?}", error),
-                    instance,
-                    None,
-                    Vec::<vk::types::CommandPool>::new(),
-                );
+                // no features or extensions were enabled in `device_create_info`
+                ash_samples::OwnedDevice::new(&instance, pdevice, device,
+                                              ash_samples::features::no_features(), Vec::new())
             }
+            Err(error) => panic!("failed to create logical device: {:?}", error),
         };
 
         let pool_create_info = vk::CommandPoolCreateInfo {
@@ -109,78 +105,36 @@ fn main() {
             queue_family_index: queue_family_index as u32,
         };
 
-        let pool = match device.create_command_pool(&pool_create_info, None) {
+        let mut pool = match device.create_command_pool(&pool_create_info, None) {
             Ok(pool) => {
                 println!("Successfully created command pool!");
-                pool
-            }
-            Err(error) => {
-                clean_up_and_panic(
-                    &format!("failed to create command pool: {:?}", error),
-                    instance,
-                    Some(device),
-                    Vec::<vk::types::CommandPool>::new(),
-                );
+                ash_samples::OwnedCommandPool::new(&device, pool)
             }
+            Err(error) => panic!("failed to create command pool: {:?}", error),
         };
 
         let command_buffer_allocate_info = vk::CommandBufferAllocateInfo {
             s_type: vk::StructureType::CommandBufferAllocateInfo,
             p_next: ptr::null(),
             command_buffer_count: 1,
-            command_pool: pool,
+            command_pool: pool.pool,
             level: vk::CommandBufferLevel::Primary,
         };
 
-        let command_buffer = match device.allocate_command_buffers(&command_buffer_allocate_info) {
+        pool.buffers = match device.allocate_command_buffers(&command_buffer_allocate_info) {
             Ok(command_buffer) => {
                 println!("Successfully allocated command buffer!");
                 command_buffer
             }
-            Err(error) => {
-                clean_up_and_panic(
-                    &format!("failed to allocate command buffer: {:?}", error),
-                    instance,
-                    Some(device),
-                    vec![pool],
-                );
-            }
+            Err(error) => panic!("failed to allocate command buffer: {:?}", error),
         };
 
+        // `pool`, `device` and `instance` are destroyed in that order as they go out of scope
         println!("Cleaning up...");
-        clean_up(instance, Some(device), vec![pool]);
-    }
-}
-
-unsafe fn clean_up_and_panic(
-    message: &str,
-    instance: Instance<V1_0>,
-    some_ldevice: Option<Device<V1_0>>,
-    pools: Vec<vk::types::CommandPool>,
-) -> ! {
-    clean_up(instance, some_ldevice, pools);
-    panic!("panic: {}", message);
-}
 
-unsafe fn clean_up(
-    instance: Instance<V1_0>,
-    some_ldevice: Option<Device<V1_0>>,
-    pools: Vec<vk::CommandPool>) {
-    match some_ldevice {
-        Some(ldevice) => {
-            for pool in pools {
-                ldevice.destroy_command_pool(pool, None);
-            }
-
-            println!("Destroying ldevice...");
-            ldevice.destroy_device(None);
-        },
-        None => {}
+        debug_callback.as_ref().map(|callback| callback.tally())
     };
 
-    println!("Destroying instance...");
-    instance.destroy_instance(None);
-
-    println!("Clean up complete.");
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
 }
-
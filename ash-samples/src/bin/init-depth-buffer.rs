@@ -0,0 +1,54 @@
+extern crate ash;
+extern crate ash_samples;
+extern crate log;
+
+use ash::vk;
+use ash_samples::QueueRole;
+
+// code is a Rust version of: https://github.com/LunarG/VulkanSamples/blob/master/API-Samples/06-init_depth_buffer/06-init_depth_buffer.cpp
+// please look at ash-tutorial.pdf for further information!
+
+fn main() {
+    ash_samples::init_logger(log::LevelFilter::Info);
+
+    // the depth buffer has to match the swapchain images it is rendered with
+    let width: u32 = 500;
+    let height: u32 = 500;
+
+    let validation_tally = unsafe {
+        let builder = ash_samples::InstanceBuilder::new("init-depth-buffer-sample");
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
+
+        // the best ranked pdevice with a graphics queue family, and a logical device with one
+        // queue from it
+        let sample = match ash_samples::HeadlessSample::new(
+            &instance, &[(QueueRole::Graphics, 1.0)]) {
+            Ok(sample) => sample,
+            Err(error) => panic!("{}", error),
+        };
+
+        let depth_format = match ash_samples::find_depth_format(&instance, sample.pdevice) {
+            Ok(depth_format) => depth_format,
+            Err(error) => panic!("{}", error),
+        };
+        println!("Depth format: {}", depth_format);
+
+        // the image, its memory and its view are released together when `depth_image` is dropped
+        let depth_image = match ash_samples::create_depth_image(
+            &sample.ldevice, depth_format, vk::Extent2D { width, height }) {
+            Ok(depth_image) => depth_image,
+            Err(error) => panic!("{}", error),
+        };
+        println!("Created {}x{} depth buffer {:?}.", width, height, depth_image.image);
+
+        println!("Cleaning up...");
+
+        debug_callback.as_ref().map(|callback| callback.tally())
+    };
+
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
+}
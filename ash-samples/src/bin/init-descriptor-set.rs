@@ -0,0 +1,79 @@
+extern crate ash;
+extern crate ash_samples;
+extern crate log;
+
+use ash::vk;
+use ash_samples::QueueRole;
+use std::mem;
+
+// code is a Rust version of: https://github.com/LunarG/VulkanSamples/blob/master/API-Samples/09-init_descriptor_set/09-init_descriptor_set.cpp
+// please look at ash-tutorial.pdf for further information!
+
+fn main() {
+    ash_samples::init_logger(log::LevelFilter::Info);
+
+    let width: u32 = 500;
+    let height: u32 = 500;
+
+    let validation_tally = unsafe {
+        let builder = ash_samples::InstanceBuilder::new("init-descriptor-set-sample");
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
+
+        // the best ranked pdevice with a graphics queue family, and a logical device with one
+        // queue from it
+        let sample = match ash_samples::HeadlessSample::new(
+            &instance, &[(QueueRole::Graphics, 1.0)]) {
+            Ok(sample) => sample,
+            Err(error) => panic!("{}", error),
+        };
+
+        let camera = ash_samples::Camera::new(width as f32 / height as f32);
+        let mvp = camera.mvp(&ash_samples::camera::identity());
+
+        let uniform_buffer = match ash_samples::create_buffer(
+            &sample.ldevice,
+            mem::size_of::<ash_samples::Mat4>() as vk::DeviceSize,
+            vk::BUFFER_USAGE_UNIFORM_BUFFER_BIT,
+            vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT | vk::MEMORY_PROPERTY_HOST_COHERENT_BIT) {
+            Ok(uniform_buffer) => uniform_buffer,
+            Err(error) => panic!("{}", error),
+        };
+        if let Err(error) = ash_samples::write_buffer(&uniform_buffer, &[mvp]) {
+            panic!("{}", error);
+        }
+
+        let set_layout = match ash_samples::create_uniform_buffer_set_layout(
+            &sample.ldevice, 0, vk::SHADER_STAGE_VERTEX_BIT) {
+            Ok(set_layout) => set_layout,
+            Err(error) => panic!("{}", error),
+        };
+
+        let _pipeline_layout =
+            match ash_samples::create_pipeline_layout(&sample.ldevice, &[set_layout.clone()]) {
+            Ok(pipeline_layout) => pipeline_layout,
+            Err(error) => panic!("{}", error),
+        };
+
+        // the set is allocated from a pool of its own, and freed along with it
+        let descriptor_pool = match ash_samples::create_uniform_buffer_descriptor_set(
+            &sample.ldevice, &set_layout, 0, &uniform_buffer) {
+            Ok(descriptor_pool) => descriptor_pool,
+            Err(error) => panic!("{}", error),
+        };
+        println!(
+            "Allocated descriptor set {:?} pointing at uniform buffer {:?}.",
+            descriptor_pool.sets[0], uniform_buffer.buffer
+        );
+
+        // the descriptor pool is destroyed before the buffer its set points at
+        println!("Cleaning up...");
+
+        debug_callback.as_ref().map(|callback| callback.tally())
+    };
+
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
+}
@@ -1,35 +1,46 @@
 extern crate ash;
 extern crate ash_samples;
+extern crate log;
 
 use ash::vk;
-use ash::Entry;
 use ash::Instance;
-use ash::Device;
-use ash::version::{InstanceV1_0, DeviceV1_0, V1_0};
+use ash::version::{InstanceV1_0, V1_0};
 use std::ptr;
 
 // code is a Rust version of: https://github.com/LunarG/VulkanSamples/blob/master/API-Samples/02-enumerate_devices/02-enumerate_devices.cpp
 // please look at ash-tutorial.pdf for further information!
 
 fn main() {
-    unsafe {
-        let (_entry, instance): (Entry<V1_0>, Instance<V1_0>) = ash_samples::init_instance_without_extensions("init-ldevice-and-queues-sample");
+    ash_samples::init_logger(log::LevelFilter::Info);
 
-        let pdevices = match instance.enumerate_physical_devices() {
+    let validation_tally = unsafe {
+        let builder = ash_samples::InstanceBuilder::new("init-ldevice-and-queues-sample");
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
+
+        let pdevices = match ash_samples::enumerate_pdevices(&instance) {
             Ok(pdevices) => pdevices,
-            Err(error) => {
-                // we should destroy the instance we have created, before panicking
-                ash_samples::destroy_instance_and_panic(
-                    &format!("failed to get list of pdevices: {:?}", error),
-                    instance,
-                );
-            }
+            Err(error) => panic!("{}", error),
         };
 
         println!("{} pdevices found.", pdevices.len());
-        if pdevices.len() == 0 {
-            ash_samples::destroy_instance_and_panic("No physical devices found!", instance);
-        }
+
+        let selector = match ash_samples::PdeviceSelector::from_args_or_env() {
+            Ok(selector) => selector,
+            Err(error) => panic!("{}", error),
+        };
+
+        let pdevices = match ash_samples::rank_pdevices(
+            &ash_samples::InstanceQuery::new(&instance),
+            pdevices,
+            &ash_samples::DefaultScorer::new(),
+            selector.as_ref(),
+        ) {
+            Ok(pdevices) => pdevices,
+            Err(error) => panic!("{}", error),
+        };
 
         let (pdevice, queue_family_index): (vk::types::PhysicalDevice, usize) =
             match find_relevant_pdevice_and_queue_family(
@@ -38,12 +49,7 @@ fn main() {
                 vec![vk::QUEUE_GRAPHICS_BIT],
             ) {
                 Some(result) => result,
-                None => {
-                    ash_samples::destroy_instance_and_panic(
-                        "Could not find a capable physical ldevice!",
-                        instance,
-                    );
-                }
+                None => panic!("Could not find a capable physical ldevice!"),
             };
 
         let qfp_info =
@@ -84,26 +90,22 @@ fn main() {
             p_enabled_features: ptr::null(),
         };
 
-        let ldevice: Device<V1_0> = match instance.create_device(pdevice, &device_create_info, None)
-        {
+        // the ldevice keeps the instance alive, and is destroyed before it
+        let _ldevice = match instance.create_device(pdevice, &device_create_info, None) {
             Ok(device) => {
                 println!("Successfully created logical ldevice.");
-                device
-            }
-            Err(error) => {
-                ash_samples::destroy_instance_and_panic(
-                    &format!("failed to create logical ldevice: {:?}", error),
-                    instance,
-                );
+                // no features or extensions were enabled in `device_create_info`
+                ash_samples::OwnedDevice::new(&instance, pdevice, device,
+                                              ash_samples::features::no_features(), Vec::new())
             }
+            Err(error) => panic!("failed to create logical ldevice: {:?}", error),
         };
 
-        println!("Destroying ldevice...");
-        ldevice.destroy_device(None);
+        debug_callback.as_ref().map(|callback| callback.tally())
+    };
 
-        println!("Destroying instance...");
-        instance.destroy_instance(None);
-    }
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
 }
 
 fn find_relevant_pdevice_and_queue_family(
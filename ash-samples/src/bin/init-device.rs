@@ -1,41 +1,52 @@
 extern crate ash;
 extern crate ash_samples;
+extern crate log;
 
 use ash::vk;
-use ash::Entry;
-use ash::Instance;
-use ash::version::{InstanceV1_0, V1_0};
+use ash::version::InstanceV1_0;
 
 // code is a Rust version of: https://github.com/LunarG/VulkanSamples/blob/master/API-Samples/02-enumerate_devices/02-enumerate_devices.cpp
 // please look at ash-tutorial.pdf for further information!
 fn main() {
-    unsafe {
-        let (_entry, instance): (Entry<V1_0>, Instance<V1_0>) = ash_samples::init_instance_without_extensions("init-device-sample");
+    ash_samples::init_logger(log::LevelFilter::Info);
 
-        let pdevices = match instance.enumerate_physical_devices() {
+    let validation_tally = unsafe {
+        let builder = ash_samples::InstanceBuilder::new("init-device-sample");
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
+
+        let pdevices = match ash_samples::enumerate_pdevices(&instance) {
             Ok(pdevices) => pdevices,
-            Err(error) => {
-                // we should destroy the instance we have created, before panicking
-                ash_samples::destroy_instance_and_panic(
-                    &format!("failed to get list of pdevices: {:?}", error),
-                    instance,
-                );
-            }
+            Err(error) => panic!("{}", error),
         };
 
         println!("{} physical devices found.", pdevices.len());
-        if pdevices.len() == 0 {
-            ash_samples::destroy_instance_and_panic("No physical devices found!", instance);
-        }
 
-        println!("Selecting the very first physical device...");
+        let selector = match ash_samples::PdeviceSelector::from_args_or_env() {
+            Ok(selector) => selector,
+            Err(error) => panic!("{}", error),
+        };
+
+        let pdevices = match ash_samples::rank_pdevices(
+            &ash_samples::InstanceQuery::new(&instance),
+            pdevices,
+            &ash_samples::DefaultScorer::new(),
+            selector.as_ref(),
+        ) {
+            Ok(pdevices) => pdevices,
+            Err(error) => panic!("{}", error),
+        };
+
+        println!("Selecting the best physical device...");
         let pdevice = pdevices[0];
 
         println!("Getting list of queue families available...");
         let queue_family_properties = instance.get_physical_device_queue_family_properties(pdevice);
 
         if queue_family_properties.len() == 0 {
-            ash_samples::destroy_instance_and_panic("No queue families found!", instance);
+            panic!("No queue families found!");
         }
 
         for (index, qfp_info) in queue_family_properties.iter().enumerate() {
@@ -51,9 +62,11 @@ fn main() {
         }
         println!("=========");
 
-        println!("Destroying instance...");
-        instance.destroy_instance(None);
-    }
+        debug_callback.as_ref().map(|callback| callback.tally())
+    };
+
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
 }
 
 fn get_queue_family_supported_ops(queue_flags: vk::types::QueueFlags) -> String {
@@ -0,0 +1,137 @@
+extern crate ash;
+extern crate ash_samples;
+extern crate log;
+
+use ash::vk;
+use ash::version::DeviceV1_0;
+use std::ptr;
+
+// code is a Rust version of: https://github.com/LunarG/VulkanSamples/blob/master/API-Samples/11-init_frame_buffers/11-init_frame_buffers.cpp
+// please look at ash-tutorial.pdf for further information!
+
+const FRAMES_IN_FLIGHT: usize = 2;
+
+fn main() {
+    ash_samples::init_logger(log::LevelFilter::Info);
+
+    let mut window = match ash_samples::SampleWindow::new(500, 500) {
+        Ok(window) => window,
+        Err(error) => panic!("{}", error),
+    };
+
+    let validation_tally = unsafe {
+        let builder = ash_samples::InstanceBuilder::new("init-frame-buffers-sample")
+            .extensions(&window.extension_names());
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
+
+        // surface, pdevice, ldevice and the swapchain's format and present mode
+        let sample = match ash_samples::WindowedSample::new(&window, &instance) {
+            Ok(sample) => sample,
+            Err(error) => panic!("{}", error),
+        };
+
+        let mut swapchain = match sample.create_swapchain(&window) {
+            Ok(swapchain) => swapchain,
+            Err(error) => panic!("{}", error),
+        };
+
+        let depth_format = match ash_samples::find_depth_format(&instance, sample.pdevice) {
+            Ok(depth_format) => depth_format,
+            Err(error) => panic!("{}", error),
+        };
+        println!("Depth format: {}", depth_format);
+
+        let render_pass = match ash_samples::create_render_pass(
+            &sample.ldevice, sample.surface_format.format, Some(depth_format.format)) {
+            Ok(render_pass) => render_pass,
+            Err(error) => panic!("{}", error),
+        };
+
+        // one framebuffer per swapchain image view, all sharing the depth image
+        let mut framebuffers = match ash_samples::SwapchainFramebuffers::new(
+            &sample.ldevice, &render_pass, Some(depth_format), &swapchain) {
+            Ok(framebuffers) => framebuffers,
+            Err(error) => panic!("{}", error),
+        };
+        println!(
+            "Created {} framebuffers of {}x{}.",
+            framebuffers.framebuffers.len(),
+            framebuffers.extent.width,
+            framebuffers.extent.height
+        );
+
+        let mut frame_loop = match ash_samples::FrameLoop::new(
+            &sample.ldevice, &sample.queues, &swapchain.sharing, FRAMES_IN_FLIGHT) {
+            Ok(frame_loop) => frame_loop,
+            Err(error) => panic!("{}", error),
+        };
+
+        // each frame runs the render pass, which only clears its attachments; the framebuffers
+        // follow the swapchain whenever it is recreated
+        let drawn = window.run_frames(&mut swapchain, &mut frame_loop, |frame| {
+            if framebuffers.rebuild_if_stale(&frame.swapchain, frame.extent, frame.generation)? {
+                println!(
+                    "Recreated {} framebuffers of {}x{}.",
+                    framebuffers.framebuffers.len(),
+                    framebuffers.extent.width,
+                    framebuffers.extent.height
+                );
+            }
+            let framebuffer = framebuffers.for_frame(frame)?;
+            record_render_pass(&sample.ldevice, &render_pass, framebuffer, frame);
+            Ok(())
+        });
+        if let Err(error) = drawn {
+            panic!("{}", error);
+        }
+
+        println!("Cleaning up...");
+        // the framebuffers go before the image views and depth image they use, which they keep
+        // alive, and before the render pass; the surface has to be destroyed before the window
+        drop(frame_loop);
+        drop(framebuffers);
+        drop(render_pass);
+        drop(swapchain);
+        drop(sample);
+        drop(window);
+
+        debug_callback.as_ref().map(|callback| callback.tally())
+    };
+
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
+}
+
+// Begins and ends the render pass on `framebuffer`, clearing color to dark blue and depth to the
+// far plane. The render pass leaves the color image in `PRESENT_SRC_KHR`.
+unsafe fn record_render_pass(
+    ldevice: &ash_samples::OwnedDevice,
+    render_pass: &ash_samples::OwnedRenderPass,
+    framebuffer: vk::Framebuffer,
+    frame: &ash_samples::Frame,
+) {
+    let clear_values = [
+        vk::ClearValue::new_color(vk::ClearColorValue::new_float32([0.0, 0.1, 0.3, 1.0])),
+        vk::ClearValue::new_depth_stencil(vk::ClearDepthStencilValue {
+            depth: 1.0,
+            stencil: 0,
+        }),
+    ];
+    let begin_info = vk::RenderPassBeginInfo {
+        s_type: vk::StructureType::RenderPassBeginInfo,
+        p_next: ptr::null(),
+        render_pass: render_pass.render_pass,
+        framebuffer,
+        render_area: vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: frame.extent,
+        },
+        clear_value_count: clear_values.len() as u32,
+        p_clear_values: clear_values.as_ptr(),
+    };
+    ldevice.cmd_begin_render_pass(frame.command_buffer, &begin_info, vk::SubpassContents::Inline);
+    ldevice.cmd_end_render_pass(frame.command_buffer);
+}
@@ -0,0 +1,51 @@
+extern crate ash;
+extern crate ash_samples;
+extern crate log;
+
+use ash::vk;
+use ash_samples::QueueRole;
+
+// code is a Rust version of: https://github.com/LunarG/VulkanSamples/blob/master/API-Samples/08-init_pipeline_layout/08-init_pipeline_layout.cpp
+// please look at ash-tutorial.pdf for further information!
+
+fn main() {
+    ash_samples::init_logger(log::LevelFilter::Info);
+
+    let validation_tally = unsafe {
+        let builder = ash_samples::InstanceBuilder::new("init-pipeline-layout-sample");
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
+
+        // the best ranked pdevice with a graphics queue family, and a logical device with one
+        // queue from it
+        let sample = match ash_samples::HeadlessSample::new(
+            &instance, &[(QueueRole::Graphics, 1.0)]) {
+            Ok(sample) => sample,
+            Err(error) => panic!("{}", error),
+        };
+
+        // one uniform buffer, the MVP matrix, for the vertex shader
+        let set_layout = match ash_samples::create_uniform_buffer_set_layout(
+            &sample.ldevice, 0, vk::SHADER_STAGE_VERTEX_BIT) {
+            Ok(set_layout) => set_layout,
+            Err(error) => panic!("{}", error),
+        };
+        println!("Created descriptor set layout {:?}.", set_layout.layout);
+
+        // keeps `set_layout` alive for as long as it exists
+        let pipeline_layout = match ash_samples::create_pipeline_layout(&sample.ldevice, &[set_layout]) {
+            Ok(pipeline_layout) => pipeline_layout,
+            Err(error) => panic!("{}", error),
+        };
+        println!("Created pipeline layout {:?}.", pipeline_layout.layout);
+
+        println!("Cleaning up...");
+
+        debug_callback.as_ref().map(|callback| callback.tally())
+    };
+
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
+}
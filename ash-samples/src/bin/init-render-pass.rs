@@ -0,0 +1,55 @@
+extern crate ash_samples;
+extern crate log;
+
+// code is a Rust version of: https://github.com/LunarG/VulkanSamples/blob/master/API-Samples/10-init_render_pass/10-init_render_pass.cpp
+// please look at ash-tutorial.pdf for further information!
+
+fn main() {
+    ash_samples::init_logger(log::LevelFilter::Info);
+
+    // the color attachment has to match the swapchain images, so a surface is needed to learn
+    // their format, even though nothing is presented here
+    let window = match ash_samples::SampleWindow::new(500, 500) {
+        Ok(window) => window,
+        Err(error) => panic!("{}", error),
+    };
+
+    let validation_tally = unsafe {
+        let builder = ash_samples::InstanceBuilder::new("init-render-pass-sample")
+            .extensions(&window.extension_names());
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
+
+        // the same setup init-swap-chain uses, so the render pass fits its swapchain images
+        let sample = match ash_samples::WindowedSample::new(&window, &instance) {
+            Ok(sample) => sample,
+            Err(error) => panic!("{}", error),
+        };
+
+        let depth_format = match ash_samples::find_depth_format(&instance, sample.pdevice) {
+            Ok(depth_format) => depth_format,
+            Err(error) => panic!("{}", error),
+        };
+        println!("Depth format: {}", depth_format);
+
+        let render_pass = match ash_samples::create_render_pass(
+            &sample.ldevice, sample.surface_format.format, Some(depth_format.format)) {
+            Ok(render_pass) => render_pass,
+            Err(error) => panic!("{}", error),
+        };
+        println!("Created render pass {:?}.", render_pass.render_pass);
+
+        println!("Cleaning up...");
+        // the surface has to be destroyed before the window it was created from
+        drop(render_pass);
+        drop(sample);
+        drop(window);
+
+        debug_callback.as_ref().map(|callback| callback.tally())
+    };
+
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
+}
@@ -0,0 +1,72 @@
+extern crate ash_samples;
+extern crate log;
+
+use ash_samples::QueueRole;
+use ash_samples::shaders;
+use std::env;
+
+// code is a Rust version of: https://github.com/LunarG/VulkanSamples/blob/master/API-Samples/11-init_shaders/11-init_shaders.cpp
+// please look at ash-tutorial.pdf for further information!
+
+// `--vert <path>` and `--frag <path>` load SPIR-V from disk instead of the shaders compiled into
+// the samples by build.rs.
+fn main() {
+    ash_samples::init_logger(log::LevelFilter::Info);
+
+    let mut vertex_path = None;
+    let mut fragment_path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--vert" {
+            vertex_path = args.next();
+        } else if arg == "--frag" {
+            fragment_path = args.next();
+        }
+    }
+
+    let validation_tally = unsafe {
+        let builder = ash_samples::InstanceBuilder::new("init-shaders-sample");
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
+
+        // the best ranked pdevice with a graphics queue family, and a logical device with one
+        // queue from it
+        let sample = match ash_samples::HeadlessSample::new(
+            &instance, &[(QueueRole::Graphics, 1.0)]) {
+            Ok(sample) => sample,
+            Err(error) => panic!("{}", error),
+        };
+
+        let vertex_shader = match vertex_path {
+            Some(ref path) => ash_samples::load_shader_module(&sample.ldevice, path),
+            None => ash_samples::create_shader_module(
+                &sample.ldevice, "draw_cube.vert", shaders::DRAW_CUBE_VERT),
+        };
+        let vertex_shader = match vertex_shader {
+            Ok(vertex_shader) => vertex_shader,
+            Err(error) => panic!("{}", error),
+        };
+        println!("Created vertex shader module from {}.", vertex_shader.name);
+
+        let fragment_shader = match fragment_path {
+            Some(ref path) => ash_samples::load_shader_module(&sample.ldevice, path),
+            None => ash_samples::create_shader_module(
+                &sample.ldevice, "draw_cube.frag", shaders::DRAW_CUBE_FRAG),
+        };
+        let fragment_shader = match fragment_shader {
+            Ok(fragment_shader) => fragment_shader,
+            Err(error) => panic!("{}", error),
+        };
+        println!("Created fragment shader module from {}.", fragment_shader.name);
+
+        // both modules are destroyed before the device they were created on
+        println!("Cleaning up...");
+
+        debug_callback.as_ref().map(|callback| callback.tally())
+    };
+
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
+}
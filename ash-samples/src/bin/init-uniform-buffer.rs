@@ -0,0 +1,65 @@
+extern crate ash;
+extern crate ash_samples;
+extern crate log;
+
+use ash::vk;
+use ash_samples::QueueRole;
+use std::mem;
+
+// code is a Rust version of: https://github.com/LunarG/VulkanSamples/blob/master/API-Samples/07-init_uniform_buffer/07-init_uniform_buffer.cpp
+// please look at ash-tutorial.pdf for further information!
+
+fn main() {
+    ash_samples::init_logger(log::LevelFilter::Info);
+
+    let width: u32 = 500;
+    let height: u32 = 500;
+
+    let validation_tally = unsafe {
+        let builder = ash_samples::InstanceBuilder::new("init-uniform-buffer-sample");
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
+
+        // the best ranked pdevice with a graphics queue family, and a logical device with one
+        // queue from it
+        let sample = match ash_samples::HeadlessSample::new(
+            &instance, &[(QueueRole::Graphics, 1.0)]) {
+            Ok(sample) => sample,
+            Err(error) => panic!("{}", error),
+        };
+
+        // the model sits at the origin; the camera includes the flip into Vulkan's clip space
+        let camera = ash_samples::Camera::new(width as f32 / height as f32);
+        let mvp = camera.mvp(&ash_samples::camera::identity());
+
+        // host visible and coherent, so the matrix can be rewritten every frame without flushes
+        let uniform_buffer = match ash_samples::create_buffer(
+            &sample.ldevice,
+            mem::size_of::<ash_samples::Mat4>() as vk::DeviceSize,
+            vk::BUFFER_USAGE_UNIFORM_BUFFER_BIT,
+            vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT | vk::MEMORY_PROPERTY_HOST_COHERENT_BIT) {
+            Ok(uniform_buffer) => uniform_buffer,
+            Err(error) => panic!("{}", error),
+        };
+
+        if let Err(error) = ash_samples::write_buffer(&uniform_buffer, &[mvp]) {
+            panic!("{}", error);
+        }
+        println!("Wrote MVP matrix to uniform buffer {:?}:", uniform_buffer.buffer);
+        for row in 0..4 {
+            println!(
+                "  {:>9.4} {:>9.4} {:>9.4} {:>9.4}",
+                mvp[0][row], mvp[1][row], mvp[2][row], mvp[3][row]
+            );
+        }
+
+        println!("Cleaning up...");
+
+        debug_callback.as_ref().map(|callback| callback.tally())
+    };
+
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
+}
@@ -0,0 +1,88 @@
+extern crate ash;
+extern crate ash_samples;
+extern crate log;
+
+use ash::vk;
+use ash::version::DeviceV1_0;
+use ash_samples::QueueRole;
+use std::env;
+use std::ptr;
+
+// Renders into an offscreen image instead of a window and writes the result to a PNG, so the output
+// can be inspected on machines without a display. `--output <path>` picks the file, which
+// defaults to `offscreen.png`.
+fn main() {
+    ash_samples::init_logger(log::LevelFilter::Info);
+
+    let mut output_path = String::from("offscreen.png");
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--output" {
+            if let Some(path) = args.next() {
+                output_path = path;
+            }
+        } else if arg.starts_with("--output=") {
+            output_path = String::from(&arg["--output=".len()..]);
+        }
+    }
+
+    let validation_tally = unsafe {
+        let builder = ash_samples::InstanceBuilder::new("render-offscreen-sample");
+        let (instance, debug_callback) = match builder.build_with_validation() {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        };
+
+        // the best ranked pdevice with a graphics queue family, and a logical device with one
+        // queue from it
+        let sample = match ash_samples::HeadlessSample::new(
+            &instance, &[(QueueRole::Graphics, 1.0)]) {
+            Ok(sample) => sample,
+            Err(error) => panic!("{}", error),
+        };
+        let graphics_qf_index = sample.queue_plan.family_index(QueueRole::Graphics).unwrap();
+
+        let command_pool =
+            match ash_samples::create_command_pool_and_buffer(&sample.ldevice, graphics_qf_index, 1) {
+            Ok(result) => result,
+            Err(err) => panic!("Failed to set up graphics command pool and buffer: {}", err),
+        };
+        let command_buffer = command_pool.buffers[0];
+
+        let target = match ash_samples::OffscreenTarget::new(&sample.ldevice, 500, 500) {
+            Ok(target) => target,
+            Err(error) => panic!("{}", error),
+        };
+
+        let begin_info = vk::CommandBufferBeginInfo {
+            s_type: vk::StructureType::CommandBufferBeginInfo,
+            p_next: ptr::null(),
+            flags: vk::COMMAND_BUFFER_USAGE_ONE_TIME_SUBMIT_BIT,
+            p_inheritance_info: ptr::null(),
+        };
+        if let Err(error) = sample.ldevice.begin_command_buffer(command_buffer, &begin_info) {
+            panic!("Failed to begin command buffer: {:?}", error);
+        }
+        target.record_clear(command_buffer, [0.2, 0.2, 0.2, 1.0]);
+        target.record_readback(command_buffer);
+        if let Err(error) = sample.ldevice.end_command_buffer(command_buffer) {
+            panic!("Failed to end command buffer: {:?}", error);
+        }
+
+        let graphics_queue = sample.queues[&QueueRole::Graphics];
+        if let Err(error) =
+            ash_samples::submit_and_wait(&sample.ldevice, graphics_queue, command_buffer) {
+            panic!("{}", error);
+        }
+
+        match target.write_png(&output_path) {
+            Ok(()) => println!("Wrote {}x{} image to {}.", 500, 500, output_path),
+            Err(error) => panic!("{}", error),
+        }
+
+        debug_callback.as_ref().map(|callback| callback.tally())
+    };
+
+    // only once every Vulkan object is destroyed
+    ash_samples::report_validation_and_exit(validation_tally);
+}
@@ -0,0 +1,208 @@
+// Just enough matrix math to build a model-view-projection matrix for the samples.
+//
+// Matrices are column-major arrays of columns, which is how GLSL expects a `mat4` in a uniform
+// buffer, and follow the OpenGL conventions `glm` uses: right-handed, looking down -z, with clip
+// space depth in [-1, 1] and y pointing up. Vulkan's clip space has y pointing down and depth in
+// [0, 1]; `clip_correction` converts between the two.
+
+pub type Vec3 = [f32; 3];
+pub type Mat4 = [[f32; 4]; 4];
+
+pub fn identity() -> Mat4 {
+    [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+// `a * b`, i.e. `b` is applied first
+pub fn multiply(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut product = [[0.0; 4]; 4];
+    for (product_column, b_column) in product.iter_mut().zip(b) {
+        *product_column = transform(a, *b_column);
+    }
+    product
+}
+
+pub fn transform(m: &Mat4, v: [f32; 4]) -> [f32; 4] {
+    let mut transformed = [0.0; 4];
+    for (row, value) in transformed.iter_mut().enumerate() {
+        *value = (0..4).map(|k| m[k][row] * v[k]).sum();
+    }
+    transformed
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: Vec3) -> Vec3 {
+    let length = dot(v, v).sqrt();
+    [v[0] / length, v[1] / length, v[2] / length]
+}
+
+// `fovy` is the vertical field of view in radians; `near` and `far` are positive distances.
+pub fn perspective(fovy: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    let f = 1.0 / (fovy / 2.0).tan();
+    [
+        [f / aspect, 0.0, 0.0, 0.0],
+        [0.0, f, 0.0, 0.0],
+        [0.0, 0.0, (far + near) / (near - far), -1.0],
+        [0.0, 0.0, 2.0 * far * near / (near - far), 0.0],
+    ]
+}
+
+// Moves `eye` to the origin, looking down -z towards `center`, with `up` pointing along +y.
+pub fn look_at(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
+    let f = normalize(sub(center, eye));
+    let s = normalize(cross(f, up));
+    let u = cross(s, f);
+    [
+        [s[0], u[0], -f[0], 0.0],
+        [s[1], u[1], -f[1], 0.0],
+        [s[2], u[2], -f[2], 0.0],
+        [-dot(s, eye), -dot(u, eye), dot(f, eye), 1.0],
+    ]
+}
+
+// Flips y and maps depth from [-1, 1] to [0, 1], turning OpenGL clip space into Vulkan's.
+pub fn clip_correction() -> Mat4 {
+    [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, -1.0, 0.0, 0.0],
+        [0.0, 0.0, 0.5, 0.0],
+        [0.0, 0.0, 0.5, 1.0],
+    ]
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pub eye: Vec3,
+    pub center: Vec3,
+    pub up: Vec3,
+    pub fovy: f32,
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Camera {
+    // where the LunarG samples put their camera
+    pub fn new(aspect: f32) -> Camera {
+        Camera {
+            eye: [-5.0, 3.0, -10.0],
+            center: [0.0, 0.0, 0.0],
+            up: [0.0, -1.0, 0.0],
+            fovy: 45.0f32.to_radians(),
+            aspect,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+
+    pub fn view(&self) -> Mat4 {
+        look_at(self.eye, self.center, self.up)
+    }
+
+    pub fn projection(&self) -> Mat4 {
+        perspective(self.fovy, self.aspect, self.near, self.far)
+    }
+
+    // clip correction × projection × view × `model`, ready for a Vulkan vertex shader
+    pub fn mvp(&self, model: &Mat4) -> Mat4 {
+        let view_projection = multiply(&self.projection(), &self.view());
+        multiply(&clip_correction(), &multiply(&view_projection, model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    // the point `m` maps `p` to, after the perspective divide
+    fn project(m: &Mat4, p: Vec3) -> Vec3 {
+        let v = transform(m, [p[0], p[1], p[2], 1.0]);
+        [v[0] / v[3], v[1] / v[3], v[2] / v[3]]
+    }
+
+    #[test]
+    fn multiply_applies_the_right_matrix_first() {
+        let translate = [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [1.0, 2.0, 3.0, 1.0],
+        ];
+        let scale = [
+            [2.0, 0.0, 0.0, 0.0],
+            [0.0, 2.0, 0.0, 0.0],
+            [0.0, 0.0, 2.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        assert_close(&project(&multiply(&translate, &scale), [1.0, 1.0, 1.0]), &[3.0, 4.0, 5.0]);
+        assert_close(&project(&multiply(&scale, &translate), [1.0, 1.0, 1.0]), &[4.0, 6.0, 8.0]);
+        assert_eq!(multiply(&scale, &identity()), scale);
+    }
+
+    #[test]
+    fn perspective_maps_near_and_far_to_the_depth_range() {
+        let m = perspective(90.0f32.to_radians(), 2.0, 1.0, 10.0);
+
+        assert_close(&project(&m, [0.0, 0.0, -1.0]), &[0.0, 0.0, -1.0]);
+        assert_close(&project(&m, [0.0, 0.0, -10.0]), &[0.0, 0.0, 1.0]);
+        // a 90 degree field of view puts the top edge at 45 degrees; the aspect ratio halves x
+        assert_close(&project(&m, [2.0, 2.0, -2.0]), &[0.5, 1.0, 1.0 / 9.0]);
+    }
+
+    #[test]
+    fn look_at_moves_the_eye_to_the_origin_facing_down_z() {
+        let m = look_at([1.0, 2.0, 3.0], [1.0, 2.0, -7.0], [0.0, 1.0, 0.0]);
+
+        assert_close(&project(&m, [1.0, 2.0, 3.0]), &[0.0, 0.0, 0.0]);
+        assert_close(&project(&m, [1.0, 2.0, -7.0]), &[0.0, 0.0, -10.0]);
+        assert_close(&project(&m, [1.0, 3.0, 3.0]), &[0.0, 1.0, 0.0]);
+
+        // looking down +x, right is +z
+        let m = look_at([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        assert_close(&project(&m, [0.0, 0.0, 1.0]), &[1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn clip_correction_flips_y_and_halves_depth() {
+        let camera = Camera {
+            eye: [0.0, 0.0, 0.0],
+            center: [0.0, 0.0, -1.0],
+            up: [0.0, 1.0, 0.0],
+            fovy: 90.0f32.to_radians(),
+            aspect: 1.0,
+            near: 1.0,
+            far: 10.0,
+        };
+        let mvp = camera.mvp(&identity());
+
+        assert_close(&project(&mvp, [0.0, 0.0, -1.0]), &[0.0, 0.0, 0.0]);
+        assert_close(&project(&mvp, [0.0, 0.0, -10.0]), &[0.0, 0.0, 1.0]);
+        // up in the world is towards the top of the framebuffer, which is -y in Vulkan
+        assert_close(&project(&mvp, [0.0, 2.0, -2.0]), &[0.0, -1.0, 5.0 / 9.0]);
+    }
+}
@@ -0,0 +1,191 @@
+// Routes `VK_EXT_debug_report` messages into the `log` crate, and keeps count of the warnings and
+// errors reported during a run.
+
+use std::env;
+use std::ffi::CStr;
+use std::io::{self, Write};
+use std::process;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ash::vk;
+use ash::extensions::DebugReport;
+use log::{self, Level, LevelFilter, Log, Metadata, Record};
+
+use error::{Error, Result};
+use owned::OwnedInstance;
+
+// set to anything but "0" to make samples exit with a non-zero code if validation reported errors
+pub const FAIL_ON_VALIDATION_ERROR_VAR: &str = "ASH_SAMPLES_FAIL_ON_VALIDATION_ERROR";
+
+pub fn fail_on_validation_error_from_env() -> bool {
+    match env::var(FAIL_ON_VALIDATION_ERROR_VAR) {
+        Ok(value) => value != "0",
+        Err(_) => false,
+    }
+}
+
+pub struct ValidationTally {
+    warnings: AtomicUsize,
+    errors: AtomicUsize,
+    fail_on_error: bool,
+}
+
+impl ValidationTally {
+    pub fn warnings(&self) -> usize {
+        self.warnings.load(Ordering::SeqCst)
+    }
+
+    pub fn errors(&self) -> usize {
+        self.errors.load(Ordering::SeqCst)
+    }
+
+    pub fn report(&self) {
+        info!(
+            "Validation reported {} warning(s) and {} error(s).",
+            self.warnings(),
+            self.errors()
+        );
+    }
+
+    // the code the sample should exit with once every Vulkan object has been destroyed
+    pub fn exit_code(&self) -> i32 {
+        match self.fail_on_error && self.errors() > 0 {
+            true => 1,
+            false => 0,
+        }
+    }
+}
+
+// Reports the tally of a run with validation and exits with its `exit_code`; does nothing without
+// one. Messages can arrive until the last Vulkan object is gone, so call it after that.
+pub fn report_validation_and_exit(tally: Option<Arc<ValidationTally>>) {
+    if let Some(tally) = tally {
+        tally.report();
+        process::exit(tally.exit_code());
+    }
+}
+
+pub struct DebugReportCallback {
+    pub instance: Rc<OwnedInstance>,
+    pub loader: DebugReport,
+    pub callback: vk::DebugReportCallbackEXT,
+    // the callback gets a raw pointer into this, so it must stay alive until the callback is destroyed
+    tally: Arc<ValidationTally>,
+}
+
+impl DebugReportCallback {
+    pub unsafe fn new(
+        instance: &Rc<OwnedInstance>,
+        flags: vk::DebugReportFlagsEXT,
+        fail_on_error: bool,
+    ) -> Result<DebugReportCallback> {
+        let loader = DebugReport::new(&instance.entry, &***instance)
+            .map_err(|missing| Error::extension_load(DebugReport::name(), missing))?;
+
+        let tally = Arc::new(ValidationTally {
+            warnings: AtomicUsize::new(0),
+            errors: AtomicUsize::new(0),
+            fail_on_error,
+        });
+
+        let create_info = vk::DebugReportCallbackCreateInfoEXT {
+            s_type: vk::StructureType::DebugReportCallbackCreateInfoExt,
+            p_next: ptr::null(),
+            flags: flags,
+            pfn_callback: debug_report_callback,
+            p_user_data: &*tally as *const ValidationTally as *mut vk::c_void,
+        };
+
+        let callback = loader
+            .create_debug_report_callback_ext(&create_info, None)
+            .map_err(|result| Error::vk("creating debug report callback", result))?;
+
+        Ok(DebugReportCallback {
+            instance: instance.clone(),
+            loader,
+            callback,
+            tally,
+        })
+    }
+
+    pub fn tally(&self) -> Arc<ValidationTally> {
+        self.tally.clone()
+    }
+}
+
+impl Drop for DebugReportCallback {
+    fn drop(&mut self) {
+        unsafe {
+            self.loader
+                .destroy_debug_report_callback_ext(self.callback, None);
+        }
+    }
+}
+
+unsafe extern "system" fn debug_report_callback(
+    flags: vk::DebugReportFlagsEXT,
+    _: vk::DebugReportObjectTypeEXT,
+    _: vk::uint64_t,
+    _: vk::size_t,
+    _: vk::int32_t,
+    p_layer_prefix: *const vk::c_char,
+    p_message: *const vk::c_char,
+    p_user_data: *mut vk::c_void,
+) -> vk::Bool32 {
+    let tally = &*(p_user_data as *const ValidationTally);
+    let layer_prefix = CStr::from_ptr(p_layer_prefix).to_string_lossy();
+    let message = CStr::from_ptr(p_message).to_string_lossy();
+
+    let level = if flags.subset(vk::DEBUG_REPORT_ERROR_BIT_EXT) {
+        tally.errors.fetch_add(1, Ordering::SeqCst);
+        Level::Error
+    } else if flags.subset(vk::DEBUG_REPORT_WARNING_BIT_EXT)
+        || flags.subset(vk::DEBUG_REPORT_PERFORMANCE_WARNING_BIT_EXT)
+    {
+        tally.warnings.fetch_add(1, Ordering::SeqCst);
+        Level::Warn
+    } else if flags.subset(vk::DEBUG_REPORT_INFORMATION_BIT_EXT) {
+        Level::Info
+    } else {
+        Level::Debug
+    };
+
+    log!(target: "vulkan", level, "[{}] {}", layer_prefix, message);
+
+    // returning false tells the layer not to abort the call that triggered the message
+    0
+}
+
+// A minimal `log` sink for the samples, printing every record to stderr.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let _ = writeln!(
+                io::stderr(),
+                "{} {}: {}",
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+pub fn init_logger(level: LevelFilter) {
+    // only the first call installs the logger; later calls just adjust the level
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level);
+}
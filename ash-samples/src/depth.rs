@@ -0,0 +1,228 @@
+// Depth buffers: choosing a depth format the pdevice can attach, and creating the image for it.
+//
+// Only `D16_UNORM` is guaranteed to be usable as a depth attachment with optimal tiling, so the
+// format is picked from a preference list by asking the pdevice about each candidate.
+
+use std::fmt;
+use std::ptr;
+use std::rc::Rc;
+
+use ash::Instance;
+use ash::vk;
+use ash::version::{DeviceV1_0, InstanceV1_0, V1_0};
+
+use error::{Error, Result};
+use memory::allocate_and_bind_image;
+use owned::{OwnedDevice, OwnedImage};
+
+// most precise first, so depth testing is as exact as the pdevice allows
+pub const DEPTH_FORMATS: [vk::Format; 5] = [
+    vk::Format::D32Sfloat,
+    vk::Format::D32SfloatS8Uint,
+    vk::Format::D24UnormS8Uint,
+    vk::Format::X8D24UnormPack32,
+    vk::Format::D16Unorm,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthFormatChoice {
+    pub format: vk::Format,
+    // optimal whenever the format allows it; some drivers only offer a format with linear tiling
+    pub tiling: vk::ImageTiling,
+}
+
+impl fmt::Display for DepthFormatChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} with {:?} tiling", self.format, self.tiling)
+    }
+}
+
+pub fn has_stencil(format: vk::Format) -> bool {
+    match format {
+        vk::Format::S8Uint
+        | vk::Format::D16UnormS8Uint
+        | vk::Format::D24UnormS8Uint
+        | vk::Format::D32SfloatS8Uint => true,
+        _ => false,
+    }
+}
+
+// the aspects a view of a depth attachment in `format` has to cover
+pub fn depth_aspect_mask(format: vk::Format) -> vk::ImageAspectFlags {
+    match has_stencil(format) {
+        true => vk::IMAGE_ASPECT_DEPTH_BIT | vk::IMAGE_ASPECT_STENCIL_BIT,
+        false => vk::IMAGE_ASPECT_DEPTH_BIT,
+    }
+}
+
+// The first of `candidates` that can be a depth attachment, according to `format_properties`.
+// Optimal tiling with any candidate beats linear tiling with a preferred one.
+pub fn choose_depth_format<F>(
+    candidates: &[vk::Format],
+    format_properties: F,
+) -> Option<DepthFormatChoice>
+where
+    F: Fn(vk::Format) -> vk::FormatProperties,
+{
+    let properties: Vec<(vk::Format, vk::FormatProperties)> = candidates
+        .iter()
+        .map(|&format| (format, format_properties(format)))
+        .collect();
+    let supports = |features: vk::FormatFeatureFlags| {
+        features.subset(vk::FORMAT_FEATURE_DEPTH_STENCIL_ATTACHMENT_BIT)
+    };
+
+    let optimal = properties
+        .iter()
+        .find(|&&(_, ref properties)| supports(properties.optimal_tiling_features))
+        .map(|&(format, _)| DepthFormatChoice {
+            format,
+            tiling: vk::ImageTiling::Optimal,
+        });
+    optimal.or_else(|| {
+        properties
+            .iter()
+            .find(|&&(_, ref properties)| supports(properties.linear_tiling_features))
+            .map(|&(format, _)| DepthFormatChoice {
+                format,
+                tiling: vk::ImageTiling::Linear,
+            })
+    })
+}
+
+// `choose_depth_format` over `DEPTH_FORMATS`, asking `pdevice`
+pub fn find_depth_format(
+    instance: &Instance<V1_0>,
+    pdevice: vk::PhysicalDevice,
+) -> Result<DepthFormatChoice> {
+    let format_properties =
+        |format: vk::Format| instance.get_physical_device_format_properties(pdevice, format);
+    choose_depth_format(&DEPTH_FORMATS, format_properties).ok_or(Error::NoDepthFormat)
+}
+
+// A single-sampled depth image covering `extent`, with its memory bound and a view for use as a
+// depth attachment. It starts out in `UNDEFINED`; the render pass moves it into its attachment
+// layout.
+pub unsafe fn create_depth_image(
+    device: &Rc<OwnedDevice>,
+    choice: DepthFormatChoice,
+    extent: vk::Extent2D,
+) -> Result<OwnedImage> {
+    let image_create_info = vk::ImageCreateInfo {
+        s_type: vk::StructureType::ImageCreateInfo,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        image_type: vk::ImageType::Type2d,
+        format: choice.format,
+        extent: vk::Extent3D {
+            width: extent.width,
+            height: extent.height,
+            depth: 1,
+        },
+        mip_levels: 1,
+        array_layers: 1,
+        samples: vk::SAMPLE_COUNT_1_BIT,
+        tiling: choice.tiling,
+        usage: vk::IMAGE_USAGE_DEPTH_STENCIL_ATTACHMENT_BIT,
+        sharing_mode: vk::SharingMode::Exclusive,
+        queue_family_index_count: 0,
+        p_queue_family_indices: ptr::null(),
+        initial_layout: vk::ImageLayout::Undefined,
+    };
+
+    let mut image = match device.create_image(&image_create_info, None) {
+        Ok(image) => OwnedImage::new(device, image),
+        Err(result) => return Err(Error::vk("creating depth image", result)),
+    };
+    // linearly tiled images may not be allowed in device local memory, so take whatever type fits
+    let properties = match choice.tiling {
+        vk::ImageTiling::Optimal => vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT,
+        _ => vk::MemoryPropertyFlags::empty(),
+    };
+    allocate_and_bind_image(&mut image, properties)?;
+
+    let view_create_info = vk::ImageViewCreateInfo {
+        s_type: vk::StructureType::ImageViewCreateInfo,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        view_type: vk::ImageViewType::Type2d,
+        format: choice.format,
+        components: vk::ComponentMapping {
+            r: vk::ComponentSwizzle::R,
+            g: vk::ComponentSwizzle::G,
+            b: vk::ComponentSwizzle::B,
+            a: vk::ComponentSwizzle::A,
+        },
+        subresource_range: vk::ImageSubresourceRange {
+            aspect_mask: depth_aspect_mask(choice.format),
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        },
+        image: image.image,
+    };
+    image.view = device
+        .create_image_view(&view_create_info, None)
+        .map_err(|result| Error::vk("creating depth image view", result))?;
+
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(optimal: bool, linear: bool) -> vk::FormatProperties {
+        let features = |supported| match supported {
+            true => vk::FORMAT_FEATURE_DEPTH_STENCIL_ATTACHMENT_BIT,
+            false => vk::FormatFeatureFlags::empty(),
+        };
+        vk::FormatProperties {
+            linear_tiling_features: features(linear),
+            optimal_tiling_features: features(optimal),
+            buffer_features: vk::FormatFeatureFlags::empty(),
+        }
+    }
+
+    #[test]
+    fn prefers_the_first_format_with_optimal_tiling() {
+        let choice = choose_depth_format(&DEPTH_FORMATS, |format| match format {
+            vk::Format::D24UnormS8Uint | vk::Format::D16Unorm => properties(true, false),
+            _ => properties(false, false),
+        });
+        assert_eq!(
+            choice,
+            Some(DepthFormatChoice {
+                format: vk::Format::D24UnormS8Uint,
+                tiling: vk::ImageTiling::Optimal,
+            })
+        );
+    }
+
+    #[test]
+    fn falls_back_to_linear_tiling() {
+        let choice = choose_depth_format(&DEPTH_FORMATS, |format| match format {
+            vk::Format::D32Sfloat => properties(false, true),
+            _ => properties(false, false),
+        });
+        assert_eq!(
+            choice,
+            Some(DepthFormatChoice {
+                format: vk::Format::D32Sfloat,
+                tiling: vk::ImageTiling::Linear,
+            })
+        );
+
+        assert_eq!(choose_depth_format(&DEPTH_FORMATS, |_| properties(false, false)), None);
+    }
+
+    #[test]
+    fn stencil_formats_need_both_aspects() {
+        assert_eq!(depth_aspect_mask(vk::Format::D16Unorm), vk::IMAGE_ASPECT_DEPTH_BIT);
+        assert_eq!(
+            depth_aspect_mask(vk::Format::D24UnormS8Uint),
+            vk::IMAGE_ASPECT_DEPTH_BIT | vk::IMAGE_ASPECT_STENCIL_BIT
+        );
+    }
+}
@@ -0,0 +1,126 @@
+// Descriptor set layouts, pipeline layouts and descriptor sets for the samples' uniform buffer.
+//
+// The samples only ever bind a single uniform buffer, holding the MVP matrix, to the vertex shader.
+// The layout describes that binding; the pipeline layout is what pipelines are created against; the
+// set, allocated from a pool of its own, points the binding at an actual buffer.
+
+use std::ptr;
+use std::rc::Rc;
+
+use ash::vk;
+use ash::version::DeviceV1_0;
+
+use error::{Error, Result};
+use owned::{OwnedBuffer, OwnedDescriptorPool, OwnedDescriptorSetLayout, OwnedDevice,
+            OwnedPipelineLayout};
+
+// A layout with a single uniform buffer at `binding`, visible to `stages`.
+pub unsafe fn create_uniform_buffer_set_layout(
+    device: &Rc<OwnedDevice>,
+    binding: u32,
+    stages: vk::ShaderStageFlags,
+) -> Result<Rc<OwnedDescriptorSetLayout>> {
+    let layout_binding = vk::DescriptorSetLayoutBinding {
+        binding,
+        descriptor_type: vk::DescriptorType::UniformBuffer,
+        descriptor_count: 1,
+        stage_flags: stages,
+        p_immutable_samplers: ptr::null(),
+    };
+    let create_info = vk::DescriptorSetLayoutCreateInfo {
+        s_type: vk::StructureType::DescriptorSetLayoutCreateInfo,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        binding_count: 1,
+        p_bindings: &layout_binding,
+    };
+
+    device
+        .create_descriptor_set_layout(&create_info, None)
+        .map(|layout| OwnedDescriptorSetLayout::new(device, layout))
+        .map_err(|result| Error::vk("creating descriptor set layout", result))
+}
+
+// A pipeline layout with `set_layouts` as sets 0, 1, ..., and no push constants.
+pub unsafe fn create_pipeline_layout(
+    device: &Rc<OwnedDevice>,
+    set_layouts: &[Rc<OwnedDescriptorSetLayout>],
+) -> Result<Rc<OwnedPipelineLayout>> {
+    let layouts: Vec<vk::DescriptorSetLayout> =
+        set_layouts.iter().map(|set_layout| set_layout.layout).collect();
+    let create_info = vk::PipelineLayoutCreateInfo {
+        s_type: vk::StructureType::PipelineLayoutCreateInfo,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        set_layout_count: layouts.len() as u32,
+        p_set_layouts: layouts.as_ptr(),
+        push_constant_range_count: 0,
+        p_push_constant_ranges: ptr::null(),
+    };
+
+    device
+        .create_pipeline_layout(&create_info, None)
+        .map(|layout| OwnedPipelineLayout::new(device, set_layouts, layout))
+        .map_err(|result| Error::vk("creating pipeline layout", result))
+}
+
+// A pool with room for one set of `layout`, and that set allocated from it as `pool.sets[0]`. The
+// set's uniform buffer at `binding` points at the whole of `buffer`; `buffer` has to outlive any
+// use of the set.
+pub unsafe fn create_uniform_buffer_descriptor_set(
+    device: &Rc<OwnedDevice>,
+    layout: &OwnedDescriptorSetLayout,
+    binding: u32,
+    buffer: &OwnedBuffer,
+) -> Result<OwnedDescriptorPool> {
+    let pool_size = vk::DescriptorPoolSize {
+        typ: vk::DescriptorType::UniformBuffer,
+        descriptor_count: 1,
+    };
+    let pool_create_info = vk::DescriptorPoolCreateInfo {
+        s_type: vk::StructureType::DescriptorPoolCreateInfo,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        max_sets: 1,
+        pool_size_count: 1,
+        p_pool_sizes: &pool_size,
+    };
+
+    let mut pool = match device.create_descriptor_pool(&pool_create_info, None) {
+        Ok(pool) => OwnedDescriptorPool::new(device, pool),
+        Err(result) => return Err(Error::vk("creating descriptor pool", result)),
+    };
+
+    let allocate_info = vk::DescriptorSetAllocateInfo {
+        s_type: vk::StructureType::DescriptorSetAllocateInfo,
+        p_next: ptr::null(),
+        descriptor_pool: pool.pool,
+        descriptor_set_count: 1,
+        p_set_layouts: &layout.layout,
+    };
+    // if allocation fails, `pool` is dropped here and destroyed with it
+    pool.sets = device
+        .allocate_descriptor_sets(&allocate_info)
+        .map_err(|result| Error::vk("allocating descriptor set", result))?;
+
+    let buffer_info = vk::DescriptorBufferInfo {
+        buffer: buffer.buffer,
+        offset: 0,
+        range: vk::VK_WHOLE_SIZE,
+    };
+    let write = vk::WriteDescriptorSet {
+        s_type: vk::StructureType::WriteDescriptorSet,
+        p_next: ptr::null(),
+        dst_set: pool.sets[0],
+        dst_binding: binding,
+        dst_array_element: 0,
+        descriptor_count: 1,
+        descriptor_type: vk::DescriptorType::UniformBuffer,
+        p_image_info: ptr::null(),
+        p_buffer_info: &buffer_info,
+        p_texel_buffer_view: ptr::null(),
+    };
+    device.update_descriptor_sets(&[write], &[]);
+
+    Ok(pool)
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+use std::rc::Rc;
+
+use ash::vk;
+use ash::Instance;
+use ash::version::{InstanceV1_0, V1_0};
+
+use error::{Error, Result};
+use features;
+use owned::{OwnedDevice, OwnedInstance};
+use queues::{QueuePlan, QueueRole};
+
+// Features and device extensions a sample needs (`required`) or can make use of (`optional`).
+pub struct DeviceRequirements {
+    pub required_features: vk::PhysicalDeviceFeatures,
+    pub optional_features: vk::PhysicalDeviceFeatures,
+    pub required_extensions: Vec<CString>,
+    pub optional_extensions: Vec<CString>,
+}
+
+// What `DeviceRequirements::negotiate` settled on for a particular pdevice.
+pub struct NegotiatedFeatures {
+    pub features: vk::PhysicalDeviceFeatures,
+    pub extensions: Vec<CString>,
+    // optional requests the pdevice could not satisfy, and which are therefore not enabled
+    pub missing_optional_features: Vec<&'static str>,
+    pub missing_optional_extensions: Vec<String>,
+}
+
+impl DeviceRequirements {
+    pub fn new() -> DeviceRequirements {
+        DeviceRequirements {
+            required_features: features::no_features(),
+            optional_features: features::no_features(),
+            required_extensions: Vec::new(),
+            optional_extensions: Vec::new(),
+        }
+    }
+
+    pub fn require_features(mut self, features: vk::PhysicalDeviceFeatures) -> DeviceRequirements {
+        self.required_features = features;
+        self
+    }
+
+    pub fn request_features(mut self, features: vk::PhysicalDeviceFeatures) -> DeviceRequirements {
+        self.optional_features = features;
+        self
+    }
+
+    pub fn require_extension(mut self, name: &CStr) -> DeviceRequirements {
+        self.required_extensions.push(name.to_owned());
+        self
+    }
+
+    pub fn request_extension(mut self, name: &CStr) -> DeviceRequirements {
+        self.optional_extensions.push(name.to_owned());
+        self
+    }
+
+    // Fails if anything required is unsupported, listing every such feature and extension.
+    pub fn negotiate(
+        &self,
+        instance: &Instance<V1_0>,
+        pdevice: vk::PhysicalDevice,
+    ) -> Result<NegotiatedFeatures> {
+        let supported_features = instance.get_physical_device_features(pdevice);
+        let supported_extensions: Vec<CString> = instance
+            .enumerate_device_extension_properties(pdevice)
+            .map_err(|result| Error::vk("enumerating device extensions", result))?
+            .iter()
+            .map(|extension| unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) }.to_owned())
+            .collect();
+
+        self.negotiate_with(&supported_features, &supported_extensions)
+    }
+
+    // `negotiate` against what a pdevice is known to support.
+    pub fn negotiate_with(
+        &self,
+        supported_features: &vk::PhysicalDeviceFeatures,
+        supported_extensions: &[CString],
+    ) -> Result<NegotiatedFeatures> {
+        let is_supported = |name: &CString| supported_extensions.contains(name);
+
+        let missing_features =
+            features::missing_features(&self.required_features, supported_features);
+        let missing_extensions: Vec<String> = self.required_extensions
+            .iter()
+            .filter(|name| !is_supported(name))
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+
+        if !missing_features.is_empty() || !missing_extensions.is_empty() {
+            return Err(Error::UnsupportedDeviceRequirements {
+                features: missing_features,
+                extensions: missing_extensions,
+            });
+        }
+
+        let mut extensions = self.required_extensions.clone();
+        let mut missing_optional_extensions = Vec::new();
+        for name in self.optional_extensions.iter() {
+            match is_supported(name) {
+                true => extensions.push(name.clone()),
+                false => missing_optional_extensions.push(name.to_string_lossy().into_owned()),
+            }
+        }
+
+        Ok(NegotiatedFeatures {
+            features: features::negotiate_features(
+                &self.required_features,
+                &self.optional_features,
+                supported_features,
+            ),
+            extensions,
+            missing_optional_features: features::missing_features(
+                &self.optional_features,
+                supported_features,
+            ),
+            missing_optional_extensions,
+        })
+    }
+}
+
+// Creates a logical device with the queues laid out in `plan` and everything from `requirements`
+// that the pdevice supports, then fetches the queues. What was actually enabled is recorded in
+// `OwnedDevice::enabled_features` and `OwnedDevice::enabled_extensions`.
+pub unsafe fn create_ldevice(
+    instance: &Rc<OwnedInstance>,
+    pdevice: vk::PhysicalDevice,
+    plan: &QueuePlan,
+    requirements: &DeviceRequirements,
+) -> Result<(Rc<OwnedDevice>, HashMap<QueueRole, vk::Queue>)> {
+    let negotiated = requirements.negotiate(instance, pdevice)?;
+    for name in negotiated.missing_optional_features.iter() {
+        warn!("optional device feature {} is not supported, skipping it.", name);
+    }
+    for name in negotiated.missing_optional_extensions.iter() {
+        warn!("optional device extension {} is not supported, skipping it.", name);
+    }
+
+    // these point into `plan` and `negotiated`, which outlive the `create_device` call
+    let queue_infos = plan.create_infos();
+    let pp_extension_names: Vec<*const c_char> =
+        negotiated.extensions.iter().map(|name| name.as_ptr()).collect();
+
+    let ldevice_create_info = vk::DeviceCreateInfo {
+        s_type: vk::StructureType::DeviceCreateInfo,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        queue_create_info_count: queue_infos.len() as u32,
+        p_queue_create_infos: queue_infos.as_ptr(),
+        enabled_layer_count: 0,
+        pp_enabled_layer_names: ptr::null(),
+        enabled_extension_count: pp_extension_names.len() as u32,
+        pp_enabled_extension_names: pp_extension_names.as_ptr(),
+        p_enabled_features: &negotiated.features,
+    };
+
+    info!("Creating logical device...");
+    let device = instance.create_device(pdevice, &ldevice_create_info, None)?;
+    let ldevice = OwnedDevice::new(
+        instance,
+        pdevice,
+        device,
+        negotiated.features,
+        negotiated.extensions,
+    );
+    let queues = plan.get_queues(&ldevice);
+
+    Ok((ldevice, queues))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use super::*;
+    use features;
+
+    fn names(names: &[&str]) -> Vec<CString> {
+        names.iter().map(|&name| CString::new(name).unwrap()).collect()
+    }
+
+    #[test]
+    fn names_unsupported_requirements() {
+        let mut required = features::no_features();
+        required.geometry_shader = 1;
+        required.sampler_anisotropy = 1;
+        let mut supported = features::no_features();
+        supported.sampler_anisotropy = 1;
+
+        let requirements = DeviceRequirements::new()
+            .require_features(required)
+            .require_extension(&CString::new("VK_KHR_swapchain").unwrap());
+
+        match requirements.negotiate_with(&supported, &names(&["VK_KHR_maintenance1"])) {
+            Err(Error::UnsupportedDeviceRequirements {
+                features,
+                extensions,
+            }) => {
+                assert_eq!(features, vec!["geometry_shader"]);
+                assert_eq!(extensions, vec!["VK_KHR_swapchain"]);
+            }
+            Err(error) => panic!("expected UnsupportedDeviceRequirements, got {:?}", error),
+            Ok(_) => panic!("expected UnsupportedDeviceRequirements, got a negotiation"),
+        }
+    }
+
+    #[test]
+    fn drops_unsupported_optional_requests() {
+        let mut optional = features::no_features();
+        optional.wide_lines = 1;
+        optional.fill_mode_non_solid = 1;
+        let mut supported = features::no_features();
+        supported.fill_mode_non_solid = 1;
+
+        let requirements = DeviceRequirements::new()
+            .request_features(optional)
+            .request_extension(&CString::new("VK_KHR_maintenance1").unwrap())
+            .request_extension(&CString::new("VK_EXT_debug_marker").unwrap());
+
+        let negotiated = requirements
+            .negotiate_with(&supported, &names(&["VK_KHR_maintenance1"]))
+            .unwrap();
+        assert_eq!(features::feature_names(&negotiated.features), vec!["fill_mode_non_solid"]);
+        assert_eq!(negotiated.extensions, names(&["VK_KHR_maintenance1"]));
+        assert_eq!(negotiated.missing_optional_features, vec!["wide_lines"]);
+        assert_eq!(negotiated.missing_optional_extensions, vec!["VK_EXT_debug_marker"]);
+    }
+
+    #[test]
+    fn empty_requirements_enable_nothing() {
+        let mut supported = features::no_features();
+        supported.geometry_shader = 1;
+
+        let negotiated = DeviceRequirements::new()
+            .negotiate_with(&supported, &names(&["VK_KHR_swapchain"]))
+            .unwrap();
+        assert!(features::feature_names(&negotiated.features).is_empty());
+        assert!(negotiated.extensions.is_empty());
+        assert!(negotiated.missing_optional_features.is_empty());
+        assert!(negotiated.missing_optional_extensions.is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+use std::error;
+use std::ffi::{CStr, NulError};
+use std::fmt;
+use std::io;
+use std::result;
+
+use ash;
+use ash::vk;
+use png;
+use winit;
+
+use shader::SpirvProblem;
+
+pub type Result<T> = result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    // the Vulkan loader library could not be opened, or is missing its core entry points
+    Loader(ash::LoadingError),
+    // `vkCreateInstance` failed, or the instance's function pointers could not be loaded
+    Instance(ash::InstanceError),
+    // `vkCreateDevice` failed, or the device's function pointers could not be loaded
+    Device(ash::DeviceError),
+    // an extension loader (`Surface::new`, `Swapchain::new`, ...) could not find some of its functions
+    ExtensionLoad {
+        extension: &'static CStr,
+        missing: Vec<&'static str>,
+    },
+    // any other Vulkan call returned an error code; `step` says what we were doing at the time
+    Vk {
+        step: &'static str,
+        result: vk::Result,
+    },
+    // layers or extensions requested from `InstanceBuilder` that the loader does not offer
+    MissingLayers(Vec<String>),
+    MissingExtensions(Vec<String>),
+    NoPhysicalDevices,
+    // no pdevice matched the `PdeviceSelector` given on the command line or in the environment
+    NoMatchingPdevice(String),
+    // a device selector given on the command line or in the environment could not be parsed
+    InvalidDeviceSelector(String),
+    // every pdevice was rejected by the `PdeviceScorer`
+    NoSuitablePdevice,
+    // none of the physical devices has a queue family with the `required` capabilities
+    NoQueueFamily {
+        required: String,
+    },
+    // required device features or extensions the pdevice does not support
+    UnsupportedDeviceRequirements {
+        features: Vec<&'static str>,
+        extensions: Vec<String>,
+    },
+    // a name passed to Vulkan contained an interior NUL byte
+    InvalidName(NulError),
+    // no memory type allowed by `type_bits` has all of the `properties` flags
+    NoMemoryType {
+        type_bits: u32,
+        properties: u32,
+    },
+    // none of the candidate depth formats can be used as a depth attachment
+    NoDepthFormat,
+    // shader code that is not a well-formed SPIR-V module; `name` says where it came from
+    InvalidSpirv {
+        name: String,
+        problem: SpirvProblem,
+    },
+    // the surface reported no usable formats
+    NoSurfaceFormats,
+    // the surface currently has no area, e.g. because its window is minimized
+    ZeroSizedSurface,
+    // a present mode name given on the command line or in the environment was not recognized
+    UnknownPresentMode(String),
+    // a swapchain sharing policy given on the command line was not recognized
+    UnknownSharingPolicy(String),
+    // a surface backend given on the command line or in the environment was not recognized
+    UnknownSurfaceBackend(String),
+    // the window is not on the display server the requested surface backend talks to
+    UnavailableSurfaceBackend(String),
+    // winit could not open the sample's window
+    Window(winit::CreationError),
+    Io(io::Error),
+    // encoding a read back image as PNG failed
+    Png(png::EncodingError),
+}
+
+impl Error {
+    pub fn vk(step: &'static str, result: vk::Result) -> Error {
+        Error::Vk { step, result }
+    }
+
+    pub fn extension_load(extension: &'static CStr, missing: Vec<&'static str>) -> Error {
+        Error::ExtensionLoad { extension, missing }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Loader(ref error) => write!(f, "failed to load Vulkan: {:?}", error),
+            Error::Instance(ref error) => write!(f, "failed to create instance: {:?}", error),
+            Error::Device(ref error) => write!(f, "failed to create logical device: {:?}", error),
+            Error::ExtensionLoad {
+                extension,
+                ref missing,
+            } => write!(
+                f,
+                "failed to load {:?}, missing functions: {}",
+                extension,
+                missing.join(", ")
+            ),
+            Error::Vk { step, result } => write!(f, "{} failed: {:?}", step, result),
+            Error::MissingLayers(ref layers) => {
+                write!(f, "missing instance layers: {}", layers.join(", "))
+            }
+            Error::MissingExtensions(ref extensions) => {
+                write!(f, "missing instance extensions: {}", extensions.join(", "))
+            }
+            Error::NoPhysicalDevices => write!(f, "no physical devices found"),
+            Error::NoMatchingPdevice(ref selector) => {
+                write!(f, "no physical device matches {}", selector)
+            }
+            Error::InvalidDeviceSelector(ref spec) => write!(
+                f,
+                "invalid device selector {} (expected index:<n>, name:<text>, 0x<vendor> or \
+                 0x<vendor>:0x<device>)",
+                spec
+            ),
+            Error::NoSuitablePdevice => {
+                write!(f, "no physical device supports the required features and extensions")
+            }
+            Error::NoQueueFamily { ref required } => write!(
+                f,
+                "no physical device has a queue family supporting: {}",
+                required
+            ),
+            Error::UnsupportedDeviceRequirements {
+                ref features,
+                ref extensions,
+            } => write!(
+                f,
+                "unsupported device features: [{}], unsupported device extensions: [{}]",
+                features.join(", "),
+                extensions.join(", ")
+            ),
+            Error::InvalidName(ref error) => write!(f, "invalid name: {}", error),
+            Error::NoMemoryType {
+                type_bits,
+                properties,
+            } => write!(
+                f,
+                "no memory type in {:#b} has properties {:#x}",
+                type_bits, properties
+            ),
+            Error::NoDepthFormat => {
+                write!(f, "no depth format is supported as a depth attachment")
+            }
+            Error::InvalidSpirv {
+                ref name,
+                ref problem,
+            } => write!(f, "{} is not valid SPIR-V: {}", name, problem),
+            Error::NoSurfaceFormats => write!(f, "the surface reports no usable formats"),
+            Error::ZeroSizedSurface => write!(f, "the surface has zero width or height"),
+            Error::UnknownPresentMode(ref name) => write!(
+                f,
+                "unknown present mode {} (expected vsync, mailbox, immediate or relaxed)",
+                name
+            ),
+            Error::UnknownSharingPolicy(ref name) => write!(
+                f,
+                "unknown sharing policy {} (expected concurrent or exclusive)",
+                name
+            ),
+            Error::UnknownSurfaceBackend(ref name) => write!(
+                f,
+                "unknown surface backend {} (expected xlib, xcb, wayland or win32)",
+                name
+            ),
+            Error::UnavailableSurfaceBackend(ref backend) => {
+                write!(f, "the window has no {} handles to create a surface from", backend)
+            }
+            Error::Window(ref error) => write!(f, "failed to create window: {}", error),
+            Error::Io(ref error) => write!(f, "I/O error: {}", error),
+            Error::Png(ref error) => write!(f, "failed to encode PNG: {}", error),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Loader(_) => "failed to load Vulkan",
+            Error::Instance(_) => "failed to create instance",
+            Error::Device(_) => "failed to create logical device",
+            Error::ExtensionLoad { .. } => "failed to load extension",
+            Error::Vk { .. } => "Vulkan call failed",
+            Error::MissingLayers(_) => "missing instance layers",
+            Error::MissingExtensions(_) => "missing instance extensions",
+            Error::NoPhysicalDevices => "no physical devices found",
+            Error::NoMatchingPdevice(_) => "no matching physical device",
+            Error::InvalidDeviceSelector(_) => "invalid device selector",
+            Error::NoSuitablePdevice => "no suitable physical device",
+            Error::NoQueueFamily { .. } => "no suitable queue family found",
+            Error::UnsupportedDeviceRequirements { .. } => "unsupported device requirements",
+            Error::InvalidName(_) => "invalid name",
+            Error::NoMemoryType { .. } => "no suitable memory type",
+            Error::NoDepthFormat => "no supported depth format",
+            Error::InvalidSpirv { .. } => "invalid SPIR-V",
+            Error::NoSurfaceFormats => "no usable surface formats",
+            Error::ZeroSizedSurface => "zero sized surface",
+            Error::UnknownPresentMode(_) => "unknown present mode",
+            Error::UnknownSharingPolicy(_) => "unknown sharing policy",
+            Error::UnknownSurfaceBackend(_) => "unknown surface backend",
+            Error::UnavailableSurfaceBackend(_) => "surface backend unavailable",
+            Error::Window(_) => "failed to create window",
+            Error::Io(_) => "I/O error",
+            Error::Png(_) => "failed to encode PNG",
+        }
+    }
+}
+
+impl From<ash::LoadingError> for Error {
+    fn from(error: ash::LoadingError) -> Error {
+        Error::Loader(error)
+    }
+}
+
+impl From<ash::InstanceError> for Error {
+    fn from(error: ash::InstanceError) -> Error {
+        Error::Instance(error)
+    }
+}
+
+impl From<ash::DeviceError> for Error {
+    fn from(error: ash::DeviceError) -> Error {
+        Error::Device(error)
+    }
+}
+
+impl From<NulError> for Error {
+    fn from(error: NulError) -> Error {
+        Error::InvalidName(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl From<png::EncodingError> for Error {
+    fn from(error: png::EncodingError) -> Error {
+        Error::Png(error)
+    }
+}
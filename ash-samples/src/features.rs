@@ -0,0 +1,163 @@
+// Field-by-field helpers for `vk::PhysicalDeviceFeatures`, which is just a struct of 55 `Bool32`s.
+
+use ash::vk;
+
+macro_rules! with_feature_fields {
+    ($m:ident) => {
+        $m!(
+            robust_buffer_access,
+            full_draw_index_uint32,
+            image_cube_array,
+            independent_blend,
+            geometry_shader,
+            tessellation_shader,
+            sample_rate_shading,
+            dual_src_blend,
+            logic_op,
+            multi_draw_indirect,
+            draw_indirect_first_instance,
+            depth_clamp,
+            depth_bias_clamp,
+            fill_mode_non_solid,
+            depth_bounds,
+            wide_lines,
+            large_points,
+            alpha_to_one,
+            multi_viewport,
+            sampler_anisotropy,
+            texture_compression_etc2,
+            texture_compression_astc_ldr,
+            texture_compression_bc,
+            occlusion_query_precise,
+            pipeline_statistics_query,
+            vertex_pipeline_stores_and_atomics,
+            fragment_stores_and_atomics,
+            shader_tessellation_and_geometry_point_size,
+            shader_image_gather_extended,
+            shader_storage_image_extended_formats,
+            shader_storage_image_multisample,
+            shader_storage_image_read_without_format,
+            shader_storage_image_write_without_format,
+            shader_uniform_buffer_array_dynamic_indexing,
+            shader_sampled_image_array_dynamic_indexing,
+            shader_storage_buffer_array_dynamic_indexing,
+            shader_storage_image_array_dynamic_indexing,
+            shader_clip_distance,
+            shader_cull_distance,
+            shader_float64,
+            shader_int64,
+            shader_int16,
+            shader_resource_residency,
+            shader_resource_min_lod,
+            sparse_binding,
+            sparse_residency_buffer,
+            sparse_residency_image2_d,
+            sparse_residency_image3_d,
+            sparse_residency2_samples,
+            sparse_residency4_samples,
+            sparse_residency8_samples,
+            sparse_residency16_samples,
+            sparse_residency_aliased,
+            variable_multisample_rate,
+            inherited_queries
+        );
+    };
+}
+
+macro_rules! feature_fns {
+    ($($field:ident),*) => {
+        pub fn no_features() -> vk::PhysicalDeviceFeatures {
+            vk::PhysicalDeviceFeatures {
+                $($field: 0,)*
+            }
+        }
+
+        // names of the features turned on in `features`
+        pub fn feature_names(features: &vk::PhysicalDeviceFeatures) -> Vec<&'static str> {
+            let mut names = Vec::new();
+            $(
+                if features.$field != 0 {
+                    names.push(stringify!($field));
+                }
+            )*
+            names
+        }
+
+        // every feature, on or off, in declaration order
+        pub fn feature_values(features: &vk::PhysicalDeviceFeatures) -> Vec<(&'static str, bool)> {
+            vec![$((stringify!($field), features.$field != 0)),*]
+        }
+
+        // names of the features turned on in `required` but not in `supported`
+        pub fn missing_features(
+            required: &vk::PhysicalDeviceFeatures,
+            supported: &vk::PhysicalDeviceFeatures,
+        ) -> Vec<&'static str> {
+            let mut missing = Vec::new();
+            $(
+                if required.$field != 0 && supported.$field == 0 {
+                    missing.push(stringify!($field));
+                }
+            )*
+            missing
+        }
+
+        // everything in `required`, plus whatever in `optional` is also in `supported`
+        pub fn negotiate_features(
+            required: &vk::PhysicalDeviceFeatures,
+            optional: &vk::PhysicalDeviceFeatures,
+            supported: &vk::PhysicalDeviceFeatures,
+        ) -> vk::PhysicalDeviceFeatures {
+            vk::PhysicalDeviceFeatures {
+                $($field: required.$field | (optional.$field & supported.$field),)*
+            }
+        }
+    };
+}
+
+with_feature_fields!(feature_fns);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_features_has_every_feature_off() {
+        let values = feature_values(&no_features());
+        assert_eq!(values.len(), 55);
+        assert!(values.iter().all(|&(_, on)| !on));
+        assert!(feature_names(&no_features()).is_empty());
+    }
+
+    #[test]
+    fn missing_features_names_required_but_unsupported() {
+        let mut required = no_features();
+        required.geometry_shader = 1;
+        required.shader_int64 = 1;
+        let mut supported = no_features();
+        supported.shader_int64 = 1;
+        supported.wide_lines = 1;
+
+        assert_eq!(missing_features(&required, &supported), vec!["geometry_shader"]);
+        assert!(missing_features(&no_features(), &supported).is_empty());
+    }
+
+    #[test]
+    fn negotiation_keeps_required_and_supported_optional_features() {
+        let mut required = no_features();
+        required.shader_int64 = 1;
+        let mut optional = no_features();
+        optional.wide_lines = 1;
+        optional.large_points = 1;
+        let mut supported = no_features();
+        supported.shader_int64 = 1;
+        supported.large_points = 1;
+        supported.depth_clamp = 1;
+
+        let negotiated = negotiate_features(&required, &optional, &supported);
+        assert_eq!(feature_names(&negotiated), vec!["large_points", "shader_int64"]);
+
+        let nothing = negotiate_features(&no_features(), &no_features(), &supported);
+        assert!(feature_names(&nothing).is_empty());
+    }
+}
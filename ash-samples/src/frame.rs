@@ -0,0 +1,369 @@
+// The acquire → record → submit → present loop, with several frames in flight.
+//
+// Each frame slot has its own command buffer, semaphores and fence, so the CPU can record frame
+// N + 1 while the GPU is still busy with frame N. The fence of a slot is waited on before the slot
+// is reused, which bounds how far ahead the CPU can get.
+
+use std::collections::HashMap;
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+
+use ash::vk;
+use ash::version::DeviceV1_0;
+
+use create_command_pool_with_flags;
+use error::{Error, Result};
+use owned::{OwnedCommandPool, OwnedDevice, OwnedSwapchain};
+use queues::QueueRole;
+use swapchain::{SwapchainManager, SwapchainSharing};
+
+// What the recording callback of `FrameLoop::draw_frame` gets to work with.
+pub struct Frame {
+    // which frame slot is being recorded, in `0..frames_in_flight`
+    pub slot: usize,
+    pub image_index: u32,
+    pub image: vk::Image,
+    pub image_view: vk::ImageView,
+    pub extent: vk::Extent2D,
+    pub command_buffer: vk::CommandBuffer,
+    // the swapchain `image` belongs to and its `SwapchainManager::generation`, for anything built
+    // on its image views
+    pub swapchain: Rc<OwnedSwapchain>,
+    pub generation: u64,
+}
+
+struct FrameSlot {
+    image_available: vk::Semaphore,
+    render_finished: vk::Semaphore,
+    // only used when images are handed to the present family with ownership transfers
+    present_ready: vk::Semaphore,
+    // signalled once everything submitted for the slot has finished executing
+    in_flight: vk::Fence,
+}
+
+pub struct FrameLoop {
+    pub device: Rc<OwnedDevice>,
+    graphics_queue: vk::Queue,
+    present_queue: vk::Queue,
+    graphics_commands: OwnedCommandPool,
+    // one buffer per slot on the present family, for the acquiring half of ownership transfers
+    present_commands: Option<OwnedCommandPool>,
+    slots: Vec<FrameSlot>,
+    current: usize,
+}
+
+unsafe fn create_semaphore(device: &OwnedDevice) -> Result<vk::Semaphore> {
+    let create_info = vk::SemaphoreCreateInfo {
+        s_type: vk::StructureType::SemaphoreCreateInfo,
+        p_next: ptr::null(),
+        flags: Default::default(),
+    };
+    device
+        .create_semaphore(&create_info, None)
+        .map_err(|result| Error::vk("creating semaphore", result))
+}
+
+// signalled, so the first wait on it returns immediately
+unsafe fn create_signalled_fence(device: &OwnedDevice) -> Result<vk::Fence> {
+    let create_info = vk::FenceCreateInfo {
+        s_type: vk::StructureType::FenceCreateInfo,
+        p_next: ptr::null(),
+        flags: vk::FENCE_CREATE_SIGNALED_BIT,
+    };
+    device
+        .create_fence(&create_info, None)
+        .map_err(|result| Error::vk("creating fence", result))
+}
+
+impl FrameLoop {
+    // `queues` as returned by `create_ldevice`, with graphics and present queues planned.
+    pub unsafe fn new(
+        device: &Rc<OwnedDevice>,
+        queues: &HashMap<QueueRole, vk::Queue>,
+        sharing: &SwapchainSharing,
+        frames_in_flight: usize,
+    ) -> Result<FrameLoop> {
+        let queue = |role: QueueRole| {
+            queues.get(&role).cloned().ok_or_else(|| Error::NoQueueFamily {
+                required: String::from("GRAPHICS, PRESENTATION"),
+            })
+        };
+
+        let graphics_commands = create_command_pool_with_flags(
+            device,
+            sharing.graphics_family,
+            vk::COMMAND_POOL_CREATE_RESET_COMMAND_BUFFER_BIT,
+            frames_in_flight as u32,
+        )?;
+        let present_commands = match sharing.needs_ownership_transfer() {
+            true => Some(create_command_pool_with_flags(
+                device,
+                sharing.present_family,
+                vk::COMMAND_POOL_CREATE_RESET_COMMAND_BUFFER_BIT,
+                frames_in_flight as u32,
+            )?),
+            false => None,
+        };
+
+        let mut frame_loop = FrameLoop {
+            device: device.clone(),
+            graphics_queue: queue(QueueRole::Graphics)?,
+            present_queue: queue(QueueRole::Present)?,
+            graphics_commands,
+            present_commands,
+            slots: Vec::new(),
+            current: 0,
+        };
+
+        // slots are pushed once complete, so `Drop` cleans up the ones created before a failure
+        for _ in 0..frames_in_flight {
+            let image_available = create_semaphore(device)?;
+            let render_finished = create_semaphore(device).map_err(|error| {
+                device.destroy_semaphore(image_available, None);
+                error
+            })?;
+            let present_ready = create_semaphore(device).map_err(|error| {
+                device.destroy_semaphore(image_available, None);
+                device.destroy_semaphore(render_finished, None);
+                error
+            })?;
+            let in_flight = create_signalled_fence(device).map_err(|error| {
+                device.destroy_semaphore(image_available, None);
+                device.destroy_semaphore(render_finished, None);
+                device.destroy_semaphore(present_ready, None);
+                error
+            })?;
+
+            frame_loop.slots.push(FrameSlot {
+                image_available,
+                render_finished,
+                present_ready,
+                in_flight,
+            });
+        }
+
+        Ok(frame_loop)
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.slots.len()
+    }
+
+    // Draws one frame: `record` is called between `begin_command_buffer` and `end_command_buffer`,
+    // and must leave `frame.image` in `PRESENT_SRC_KHR`, as a render pass targeting the swapchain
+    // does. Returns `false` if the frame was skipped because the swapchain is out of date or the
+    // window is minimized; `swapchain` is recreated on a later call once that is possible. If
+    // recording fails, the acquired image is still handed back by presenting it without any
+    // rendering, and the recording error is returned; the slot stays usable either way.
+    pub unsafe fn draw_frame<F: FnOnce(&Frame) -> Result<()>>(
+        &mut self,
+        swapchain: &mut SwapchainManager,
+        record: F,
+    ) -> Result<bool> {
+        let device = self.device.clone();
+        let slot_index = self.current;
+
+        device
+            .wait_for_fences(&[self.slots[slot_index].in_flight], true, u64::max_value())
+            .map_err(|result| Error::vk("waiting for frame fence", result))?;
+
+        swapchain.recreate_if_out_of_date()?;
+        if swapchain.is_out_of_date() {
+            return Ok(false);
+        }
+
+        // an out of date swapchain acquires nothing and leaves the semaphore unsignalled, so the
+        // slot can simply be used again once the swapchain has been replaced
+        let image_index = match swapchain
+            .acquire_next_image(self.slots[slot_index].image_available, vk::Fence::null())?
+        {
+            Some(image_index) => image_index,
+            None => return Ok(false),
+        };
+
+        let command_buffer = self.graphics_commands.buffers[slot_index];
+        let present_buffer = self
+            .present_commands
+            .as_ref()
+            .map(|present_commands| present_commands.buffers[slot_index]);
+        let begin_info = vk::CommandBufferBeginInfo {
+            s_type: vk::StructureType::CommandBufferBeginInfo,
+            p_next: ptr::null(),
+            flags: vk::COMMAND_BUFFER_USAGE_ONE_TIME_SUBMIT_BIT,
+            p_inheritance_info: ptr::null(),
+        };
+
+        // Everything is recorded before the fence is reset. The pools allow individual resets, so
+        // beginning implicitly resets a buffer.
+        let recorded = device
+            .begin_command_buffer(command_buffer, &begin_info)
+            .map_err(|result| Error::vk("beginning frame command buffer", result))
+            .and_then(|_| {
+                record(&Frame {
+                    slot: slot_index,
+                    image_index,
+                    image: swapchain.swapchain.images[image_index as usize],
+                    image_view: swapchain.swapchain.image_views[image_index as usize],
+                    extent: swapchain.extent,
+                    command_buffer,
+                    swapchain: swapchain.swapchain.clone(),
+                    generation: swapchain.generation,
+                })
+            })
+            .and_then(|_| {
+                swapchain.record_release_to_present(command_buffer, image_index);
+                device
+                    .end_command_buffer(command_buffer)
+                    .map_err(|result| Error::vk("ending frame command buffer", result))
+            })
+            .and_then(|_| match present_buffer {
+                Some(present_buffer) => {
+                    device
+                        .begin_command_buffer(present_buffer, &begin_info)
+                        .map_err(|result| Error::vk("beginning ownership transfer", result))?;
+                    swapchain.record_acquire_for_present(present_buffer, image_index);
+                    device
+                        .end_command_buffer(present_buffer)
+                        .map_err(|result| Error::vk("ending ownership transfer", result))
+                }
+                None => Ok(()),
+            });
+
+        let present_wait = match present_buffer {
+            Some(_) => self.slots[slot_index].present_ready,
+            None => self.slots[slot_index].render_finished,
+        };
+
+        device
+            .reset_fences(&[self.slots[slot_index].in_flight])
+            .map_err(|result| Error::vk("resetting frame fence", result))?;
+
+        let (submitted, recording_error) = match recorded {
+            Ok(()) => (self.submit_frame(slot_index, command_buffer, present_buffer), None),
+            // the acquired image still has to go back to the swapchain, so it is presented as is
+            Err(error) => (self.submit_abandoned_frame(slot_index, present_wait), Some(error)),
+        };
+        if let Err(error) = submitted {
+            // the fence may never be signalled now, so the next wait on the slot needs a new one;
+            // the submit error is the one worth reporting
+            let _ = self.replace_fence(slot_index);
+            return Err(error);
+        }
+
+        swapchain.present(self.present_queue, image_index, &[present_wait])?;
+
+        self.current = (self.current + 1) % self.slots.len();
+        match recording_error {
+            Some(error) => Err(error),
+            None => Ok(true),
+        }
+    }
+
+    // Submits the recorded frame, followed by the acquiring half of its ownership transfer if
+    // `present_buffer` is given. The fence goes on the last submission, which waits on all earlier
+    // ones.
+    unsafe fn submit_frame(
+        &self,
+        slot_index: usize,
+        command_buffer: vk::CommandBuffer,
+        present_buffer: Option<vk::CommandBuffer>,
+    ) -> Result<()> {
+        let slot = &self.slots[slot_index];
+        let graphics_fence = match present_buffer {
+            Some(_) => vk::Fence::null(),
+            None => slot.in_flight,
+        };
+        let wait_stage = vk::PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT;
+        let graphics_submit = vk::SubmitInfo {
+            s_type: vk::StructureType::SubmitInfo,
+            p_next: ptr::null(),
+            wait_semaphore_count: 1,
+            p_wait_semaphores: &slot.image_available,
+            p_wait_dst_stage_mask: &wait_stage,
+            command_buffer_count: 1,
+            p_command_buffers: &command_buffer,
+            signal_semaphore_count: 1,
+            p_signal_semaphores: &slot.render_finished,
+        };
+        self.device
+            .queue_submit(self.graphics_queue, &[graphics_submit], graphics_fence)
+            .map_err(|result| Error::vk("submitting frame", result))?;
+
+        if let Some(present_buffer) = present_buffer {
+            let transfer_wait_stage = vk::PIPELINE_STAGE_ALL_COMMANDS_BIT;
+            let transfer_submit = vk::SubmitInfo {
+                s_type: vk::StructureType::SubmitInfo,
+                p_next: ptr::null(),
+                wait_semaphore_count: 1,
+                p_wait_semaphores: &slot.render_finished,
+                p_wait_dst_stage_mask: &transfer_wait_stage,
+                command_buffer_count: 1,
+                p_command_buffers: &present_buffer,
+                signal_semaphore_count: 1,
+                p_signal_semaphores: &slot.present_ready,
+            };
+            self.device
+                .queue_submit(self.present_queue, &[transfer_submit], slot.in_flight)
+                .map_err(|result| Error::vk("submitting ownership transfer", result))?;
+        }
+        Ok(())
+    }
+
+    // Submits an empty batch in place of a frame whose recording failed. It only waits for the
+    // acquire and signals `present_wait`, so the image can be presented unrendered.
+    unsafe fn submit_abandoned_frame(
+        &self,
+        slot_index: usize,
+        present_wait: vk::Semaphore,
+    ) -> Result<()> {
+        let slot = &self.slots[slot_index];
+        let wait_stage = vk::PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT;
+        let empty_submit = vk::SubmitInfo {
+            s_type: vk::StructureType::SubmitInfo,
+            p_next: ptr::null(),
+            wait_semaphore_count: 1,
+            p_wait_semaphores: &slot.image_available,
+            p_wait_dst_stage_mask: &wait_stage,
+            command_buffer_count: 0,
+            p_command_buffers: ptr::null(),
+            signal_semaphore_count: 1,
+            p_signal_semaphores: &present_wait,
+        };
+        self.device
+            .queue_submit(self.graphics_queue, &[empty_submit], slot.in_flight)
+            .map_err(|result| Error::vk("submitting abandoned frame", result))
+    }
+
+    // Gives a slot whose fence may never be signalled a fresh, signalled one. The old fence is only
+    // destroyed once the device is idle and no submission can still use it.
+    unsafe fn replace_fence(&mut self, slot_index: usize) -> Result<()> {
+        self.wait_idle()?;
+        let fence = create_signalled_fence(&self.device)?;
+        let abandoned = mem::replace(&mut self.slots[slot_index].in_flight, fence);
+        self.device.destroy_fence(abandoned, None);
+        Ok(())
+    }
+
+    // Blocks until the GPU has finished every submitted frame, e.g. before the window goes away.
+    pub unsafe fn wait_idle(&self) -> Result<()> {
+        self.device
+            .device_wait_idle()
+            .map_err(|result| Error::vk("waiting for device idle", result))
+    }
+}
+
+impl Drop for FrameLoop {
+    fn drop(&mut self) {
+        unsafe {
+            // none of the semaphores or fences may still be in use
+            let _ = self.device.device_wait_idle();
+            for slot in self.slots.iter() {
+                self.device.destroy_semaphore(slot.image_available, None);
+                self.device.destroy_semaphore(slot.render_finished, None);
+                self.device.destroy_semaphore(slot.present_ready, None);
+                self.device.destroy_fence(slot.in_flight, None);
+            }
+        }
+    }
+}
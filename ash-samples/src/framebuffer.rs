@@ -0,0 +1,125 @@
+// A framebuffer per swapchain image, for a render pass created with `create_render_pass`.
+//
+// Every framebuffer shares a single depth image, sized like the swapchain. Both are rebuilt when
+// the swapchain is recreated: `SwapchainManager` bumps its generation, and `for_frame` notices the
+// `Frame` carrying a newer one. The swapchain the framebuffers were built on is held on to until
+// then, so its image views are never destroyed before the framebuffers using them.
+
+use std::ptr;
+use std::rc::Rc;
+
+use ash::vk;
+use ash::version::DeviceV1_0;
+
+use depth::{create_depth_image, DepthFormatChoice};
+use error::{Error, Result};
+use frame::Frame;
+use owned::{OwnedDevice, OwnedFramebuffer, OwnedImage, OwnedRenderPass, OwnedSwapchain};
+use swapchain::SwapchainManager;
+
+pub struct SwapchainFramebuffers {
+    // fields are dropped in order: the framebuffers go before the views they were created from and
+    // before the render pass
+    pub framebuffers: Vec<OwnedFramebuffer>,
+    pub depth: Option<OwnedImage>,
+    swapchain: Option<Rc<OwnedSwapchain>>,
+    pub render_pass: Rc<OwnedRenderPass>,
+    pub device: Rc<OwnedDevice>,
+    depth_format: Option<DepthFormatChoice>,
+    pub extent: vk::Extent2D,
+    // the `SwapchainManager::generation` the framebuffers were built for, `None` before the first
+    // build or after a failed one
+    generation: Option<u64>,
+}
+
+impl SwapchainFramebuffers {
+    // `depth_format` has to be the depth format `render_pass` was created with, or `None` if it
+    // has no depth attachment.
+    pub unsafe fn new(
+        device: &Rc<OwnedDevice>,
+        render_pass: &Rc<OwnedRenderPass>,
+        depth_format: Option<DepthFormatChoice>,
+        swapchain: &SwapchainManager,
+    ) -> Result<SwapchainFramebuffers> {
+        let mut framebuffers = SwapchainFramebuffers {
+            framebuffers: Vec::new(),
+            depth: None,
+            swapchain: None,
+            render_pass: render_pass.clone(),
+            device: device.clone(),
+            depth_format,
+            extent: swapchain.extent,
+            generation: None,
+        };
+        framebuffers.rebuild_if_stale(
+            &swapchain.swapchain,
+            swapchain.extent,
+            swapchain.generation,
+        )?;
+        Ok(framebuffers)
+    }
+
+    // The framebuffer for `frame`'s image, after rebuilding everything if the swapchain has been
+    // recreated since the last call. Meant for `FrameLoop::draw_frame`'s recording callback, which
+    // only runs once the device is idle after a recreation.
+    pub unsafe fn for_frame(&mut self, frame: &Frame) -> Result<vk::Framebuffer> {
+        self.rebuild_if_stale(&frame.swapchain, frame.extent, frame.generation)?;
+        Ok(self.framebuffers[frame.image_index as usize].framebuffer)
+    }
+
+    // Returns whether anything was rebuilt. None of the old framebuffers may still be in use.
+    pub unsafe fn rebuild_if_stale(
+        &mut self,
+        swapchain: &Rc<OwnedSwapchain>,
+        extent: vk::Extent2D,
+        generation: u64,
+    ) -> Result<bool> {
+        if self.generation == Some(generation) {
+            return Ok(false);
+        }
+
+        // tear down in the same order as dropping would; the old swapchain's views go last
+        self.generation = None;
+        self.framebuffers.clear();
+        self.depth = None;
+        self.swapchain = Some(swapchain.clone());
+        self.extent = extent;
+
+        if let Some(depth_format) = self.depth_format {
+            self.depth = Some(create_depth_image(&self.device, depth_format, extent)?);
+        }
+
+        for &view in swapchain.image_views.iter() {
+            let framebuffer = self.create_framebuffer(view)?;
+            self.framebuffers.push(framebuffer);
+        }
+
+        self.generation = Some(generation);
+        Ok(true)
+    }
+
+    // attachments in the order `create_render_pass` numbers them: color, then depth
+    unsafe fn create_framebuffer(&self, color_view: vk::ImageView) -> Result<OwnedFramebuffer> {
+        let mut attachments = vec![color_view];
+        if let Some(ref depth) = self.depth {
+            attachments.push(depth.view);
+        }
+
+        let create_info = vk::FramebufferCreateInfo {
+            s_type: vk::StructureType::FramebufferCreateInfo,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            render_pass: self.render_pass.render_pass,
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            width: self.extent.width,
+            height: self.extent.height,
+            layers: 1,
+        };
+
+        self.device
+            .create_framebuffer(&create_info, None)
+            .map(|framebuffer| OwnedFramebuffer::new(&self.device, &self.render_pass, framebuffer))
+            .map_err(|result| Error::vk("creating framebuffer", result))
+    }
+}
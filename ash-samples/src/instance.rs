@@ -0,0 +1,364 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+use std::rc::Rc;
+
+use ash;
+use ash::vk;
+use ash::Entry;
+use ash::extensions::DebugReport;
+use ash::Instance;
+use ash::version::{EntryV1_0, V1_0};
+
+use debug::{fail_on_validation_error_from_env, DebugReportCallback};
+use error::{Error, Result};
+use owned::OwnedInstance;
+
+// Collects everything that goes into `vk::ApplicationInfo` and `vk::InstanceCreateInfo`. Names are
+// only turned into `CString`s inside `build`, so every pointer handed to Vulkan stays valid until
+// `create_instance` has returned.
+pub struct InstanceBuilder {
+    app_name: Vec<u8>,
+    app_version: u32,
+    engine_name: Vec<u8>,
+    engine_version: u32,
+    api_version: u32,
+    layers: Vec<Vec<u8>>,
+    optional_layers: Vec<Vec<u8>>,
+    extensions: Vec<Vec<u8>>,
+    // enabled if present, otherwise dropped with a warning instead of failing `build`
+    optional_extensions: Vec<Vec<u8>>,
+}
+
+impl InstanceBuilder {
+    pub fn new<T: Into<Vec<u8>>>(app_name: T) -> InstanceBuilder {
+        InstanceBuilder {
+            app_name: app_name.into(),
+            app_version: 0,
+            engine_name: b"ash-samples".to_vec(),
+            engine_version: ash::vk_make_version!(0, 1, 0),
+            api_version: ash::vk_make_version!(1, 0, 0),
+            layers: Vec::new(),
+            optional_layers: Vec::new(),
+            extensions: Vec::new(),
+            optional_extensions: Vec::new(),
+        }
+    }
+
+    pub fn app_version(mut self, version: u32) -> InstanceBuilder {
+        self.app_version = version;
+        self
+    }
+
+    pub fn engine_name<T: Into<Vec<u8>>>(mut self, name: T) -> InstanceBuilder {
+        self.engine_name = name.into();
+        self
+    }
+
+    pub fn engine_version(mut self, version: u32) -> InstanceBuilder {
+        self.engine_version = version;
+        self
+    }
+
+    pub fn api_version(mut self, version: u32) -> InstanceBuilder {
+        self.api_version = version;
+        self
+    }
+
+    pub fn layer<T: Into<Vec<u8>>>(mut self, name: T) -> InstanceBuilder {
+        self.layers.push(name.into());
+        self
+    }
+
+    pub fn optional_layer<T: Into<Vec<u8>>>(mut self, name: T) -> InstanceBuilder {
+        self.optional_layers.push(name.into());
+        self
+    }
+
+    pub fn extension<T: Into<Vec<u8>>>(mut self, name: T) -> InstanceBuilder {
+        self.extensions.push(name.into());
+        self
+    }
+
+    pub fn extensions(mut self, names: &[&CStr]) -> InstanceBuilder {
+        self.extensions
+            .extend(names.iter().map(|name| name.to_bytes().to_vec()));
+        self
+    }
+
+    pub fn optional_extension<T: Into<Vec<u8>>>(mut self, name: T) -> InstanceBuilder {
+        self.optional_extensions.push(name.into());
+        self
+    }
+
+    // Builds the instance with the standard validation layer and the debug report extension where
+    // they are available, and routes their warnings and errors into `log` through the returned
+    // callback. Drop the callback after everything created from the instance, so that messages
+    // about their destruction are still seen.
+    pub unsafe fn build_with_validation(
+        self,
+    ) -> Result<(Rc<OwnedInstance>, Option<DebugReportCallback>)> {
+        let instance = self.optional_layer("VK_LAYER_LUNARG_standard_validation")
+            .optional_extension(DebugReport::name().to_bytes())
+            .build()?;
+
+        let debug_callback = match instance.has_extension(DebugReport::name()) {
+            true => Some(DebugReportCallback::new(
+                &instance,
+                vk::DEBUG_REPORT_ERROR_BIT_EXT | vk::DEBUG_REPORT_WARNING_BIT_EXT
+                    | vk::DEBUG_REPORT_PERFORMANCE_WARNING_BIT_EXT,
+                fail_on_validation_error_from_env(),
+            )?),
+            false => None,
+        };
+        Ok((instance, debug_callback))
+    }
+
+    pub unsafe fn build(&self) -> Result<Rc<OwnedInstance>> {
+        let app_name = CString::new(self.app_name.clone())?;
+        let engine_name = CString::new(self.engine_name.clone())?;
+        let mut layers = to_cstrings(&self.layers)?;
+        let optional_layers = to_cstrings(&self.optional_layers)?;
+        let mut extensions = to_cstrings(&self.extensions)?;
+        let optional_extensions = to_cstrings(&self.optional_extensions)?;
+
+        info!("Loading Vulkan...");
+        let entry: Entry<V1_0> = Entry::new()?;
+
+        check_layers(&entry, &layers)?;
+
+        for name in optional_layers {
+            match check_layers(&entry, &[name.clone()]) {
+                Ok(()) => layers.push(name),
+                Err(Error::MissingLayers(_)) => warn!(
+                    "optional instance layer {} is not available, skipping it.",
+                    name.to_string_lossy()
+                ),
+                Err(error) => return Err(error),
+            }
+        }
+
+        // extensions may come from the loader and drivers, or from one of the enabled layers
+        let mut available = enumerate_instance_extensions(&entry, None)?;
+        for layer in layers.iter() {
+            available.extend(enumerate_instance_extensions(&entry, Some(layer.as_c_str()))?);
+        }
+
+        check_extensions(&available, &extensions)?;
+
+        for name in optional_extensions {
+            match available.iter().any(|extension| extension.name.as_bytes() == name.to_bytes()) {
+                true => extensions.push(name),
+                false => warn!(
+                    "optional instance extension {} is not available, skipping it.",
+                    name.to_string_lossy()
+                ),
+            }
+        }
+
+        // a name both required and optional, or given twice, must still only be enabled once
+        let layers = dedup_names(layers);
+        let extensions = dedup_names(extensions);
+
+        info!("Creating ApplicationInfo...");
+        let appinfo = vk::ApplicationInfo {
+            s_type: vk::StructureType::ApplicationInfo,
+            p_next: ptr::null(),
+            p_application_name: app_name.as_ptr(),
+            application_version: self.app_version,
+            p_engine_name: engine_name.as_ptr(),
+            engine_version: self.engine_version,
+            api_version: self.api_version,
+        };
+
+        let pp_layer_names: Vec<*const c_char> = layers.iter().map(|name| name.as_ptr()).collect();
+        let pp_extension_names: Vec<*const c_char> =
+            extensions.iter().map(|name| name.as_ptr()).collect();
+
+        info!("Creating InstanceCreateInfo...");
+        let create_info = vk::InstanceCreateInfo {
+            s_type: vk::StructureType::InstanceCreateInfo,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            p_application_info: &appinfo,
+            pp_enabled_layer_names: pp_layer_names.as_ptr(),
+            enabled_layer_count: pp_layer_names.len() as u32,
+            pp_enabled_extension_names: pp_extension_names.as_ptr(),
+            enabled_extension_count: pp_extension_names.len() as u32,
+        };
+
+        info!("Creating instance...");
+        let instance: Instance<V1_0> = entry.create_instance(&create_info, None)?;
+
+        Ok(OwnedInstance::new(entry, instance, extensions))
+    }
+}
+
+fn to_cstrings(names: &[Vec<u8>]) -> Result<Vec<CString>> {
+    let mut cstrings = Vec::with_capacity(names.len());
+    for name in names.iter() {
+        cstrings.push(CString::new(name.clone())?);
+    }
+    Ok(cstrings)
+}
+
+// `names` without repeats, each kept where it first appeared
+fn dedup_names(names: Vec<CString>) -> Vec<CString> {
+    let mut unique: Vec<CString> = Vec::with_capacity(names.len());
+    for name in names {
+        if !unique.contains(&name) {
+            unique.push(name);
+        }
+    }
+    unique
+}
+
+pub struct ExtensionInfo {
+    pub name: String,
+    pub spec_version: u32,
+}
+
+pub struct LayerInfo {
+    pub name: String,
+    pub description: String,
+    pub spec_version: u32,
+    pub implementation_version: u32,
+    // extensions that only become available when this layer is enabled
+    pub extensions: Vec<ExtensionInfo>,
+}
+
+// Lists the instance extensions offered by `layer`, or by the loader and drivers if `layer` is
+// `None`. ash's `enumerate_instance_extension_properties` only covers the latter, so we go through
+// the raw function pointer.
+pub fn enumerate_instance_extensions(
+    entry: &Entry<V1_0>,
+    layer: Option<&CStr>,
+) -> Result<Vec<ExtensionInfo>> {
+    let p_layer_name = match layer {
+        Some(name) => name.as_ptr(),
+        None => ptr::null(),
+    };
+
+    let properties = unsafe {
+        loop {
+            let mut num = 0;
+            let result = entry.fp_v1_0().enumerate_instance_extension_properties(
+                p_layer_name,
+                &mut num,
+                ptr::null_mut(),
+            );
+            if result != vk::Result::Success {
+                return Err(Error::vk("enumerating instance extensions", result));
+            }
+
+            let mut data: Vec<vk::ExtensionProperties> = Vec::with_capacity(num as usize);
+            let result = entry.fp_v1_0().enumerate_instance_extension_properties(
+                p_layer_name,
+                &mut num,
+                data.as_mut_ptr(),
+            );
+            match result {
+                vk::Result::Success => {
+                    data.set_len(num as usize);
+                    break data;
+                }
+                // more extensions showed up between the two calls, e.g. a driver was installed;
+                // `num` was only big enough for the old count, so start over
+                vk::Result::Incomplete => continue,
+                result => return Err(Error::vk("enumerating instance extensions", result)),
+            }
+        }
+    };
+
+    Ok(properties
+        .iter()
+        .map(|extension| ExtensionInfo {
+            name: unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) }
+                .to_string_lossy()
+                .into_owned(),
+            spec_version: extension.spec_version,
+        })
+        .collect())
+}
+
+pub fn enumerate_instance_layers(entry: &Entry<V1_0>) -> Result<Vec<LayerInfo>> {
+    let properties = entry
+        .enumerate_instance_layer_properties()
+        .map_err(|result| Error::vk("enumerating instance layers", result))?;
+
+    let mut layers = Vec::with_capacity(properties.len());
+    for layer in properties.iter() {
+        let name = unsafe { CStr::from_ptr(layer.layer_name.as_ptr()) };
+        layers.push(LayerInfo {
+            name: name.to_string_lossy().into_owned(),
+            description: unsafe { CStr::from_ptr(layer.description.as_ptr()) }
+                .to_string_lossy()
+                .into_owned(),
+            spec_version: layer.spec_version,
+            implementation_version: layer.implementation_version,
+            extensions: enumerate_instance_extensions(entry, Some(name))?,
+        });
+    }
+
+    Ok(layers)
+}
+
+fn check_layers(entry: &Entry<V1_0>, layers: &[CString]) -> Result<()> {
+    let available = entry
+        .enumerate_instance_layer_properties()
+        .map_err(|result| Error::vk("enumerating instance layers", result))?;
+
+    let missing: Vec<String> = layers
+        .iter()
+        .filter(|name| {
+            !available.iter().any(|layer| unsafe {
+                CStr::from_ptr(layer.layer_name.as_ptr()) == name.as_c_str()
+            })
+        })
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect();
+
+    match missing.len() {
+        0 => Ok(()),
+        _ => Err(Error::MissingLayers(missing)),
+    }
+}
+
+fn check_extensions(available: &[ExtensionInfo], extensions: &[CString]) -> Result<()> {
+    let missing: Vec<String> = extensions
+        .iter()
+        .filter(|name| {
+            !available
+                .iter()
+                .any(|extension| extension.name.as_bytes() == name.to_bytes())
+        })
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect();
+
+    match missing.len() {
+        0 => Ok(()),
+        _ => Err(Error::MissingExtensions(missing)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedup_names_keeps_first_occurrences_in_order() {
+        let names = to_cstrings(&[
+            b"VK_KHR_surface".to_vec(),
+            b"VK_EXT_debug_report".to_vec(),
+            b"VK_KHR_surface".to_vec(),
+            b"VK_KHR_xlib_surface".to_vec(),
+            b"VK_EXT_debug_report".to_vec(),
+        ]).unwrap();
+        let expected = to_cstrings(&[
+            b"VK_KHR_surface".to_vec(),
+            b"VK_EXT_debug_report".to_vec(),
+            b"VK_KHR_xlib_surface".to_vec(),
+        ]).unwrap();
+        assert_eq!(dedup_names(names), expected);
+    }
+}
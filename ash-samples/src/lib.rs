@@ -1,59 +1,85 @@
 #![feature(use_extern_macros)]
 
 extern crate ash;
-
-use std::ffi::CString;
+#[macro_use]
+extern crate log;
+extern crate png;
+#[macro_use]
+extern crate serde_json;
+#[cfg(windows)]
+extern crate winapi;
+extern crate winit;
+
+pub mod camera;
+pub mod debug;
+pub mod depth;
+pub mod descriptors;
+pub mod device;
+pub mod error;
+pub mod features;
+pub mod frame;
+pub mod framebuffer;
+pub mod instance;
+pub mod memory;
+pub mod offscreen;
+pub mod owned;
+pub mod pdevice;
+pub mod query;
+pub mod queues;
+pub mod render_pass;
+pub mod report;
+pub mod sample;
+pub mod shader;
+// the shaders in `shaders/`, compiled to SPIR-V by build.rs
+pub mod shaders {
+    include!(concat!(env!("OUT_DIR"), "/shaders.rs"));
+}
+pub mod surface;
+pub mod swapchain;
+#[cfg(test)]
+mod testing;
+
+pub use camera::{Camera, Mat4};
+pub use debug::{fail_on_validation_error_from_env, init_logger, report_validation_and_exit,
+                DebugReportCallback, ValidationTally};
+pub use depth::{create_depth_image, find_depth_format, DepthFormatChoice};
+pub use descriptors::{create_pipeline_layout, create_uniform_buffer_descriptor_set,
+                      create_uniform_buffer_set_layout};
+pub use device::{create_ldevice, DeviceRequirements, NegotiatedFeatures};
+pub use error::{Error, Result};
+pub use frame::{Frame, FrameLoop};
+pub use framebuffer::SwapchainFramebuffers;
+pub use instance::{enumerate_instance_extensions, enumerate_instance_layers, ExtensionInfo,
+                   InstanceBuilder, LayerInfo};
+pub use memory::{allocate_and_bind_buffer, allocate_and_bind_image, create_buffer, memory_type_index,
+                 write_buffer};
+pub use offscreen::{submit_and_wait, write_png, OffscreenTarget};
+pub use owned::{OwnedBuffer, OwnedCommandPool, OwnedDescriptorPool, OwnedDescriptorSetLayout,
+                OwnedDevice, OwnedFramebuffer, OwnedImage, OwnedInstance, OwnedPipelineLayout,
+                OwnedRenderPass, OwnedSurface, OwnedSwapchain};
+pub use pdevice::{rank_pdevices, DefaultScorer, PdeviceScorer, PdeviceSelector};
+pub use query::{find_pdevice_with_queue_family_supporting_graphics_and_presentation,
+                find_relevant_pdevice_and_queue_family, InstanceQuery, PdeviceQuery};
+pub use queues::{QueuePlan, QueueRole};
+pub use render_pass::create_render_pass;
+pub use report::{format_report, pdevice_report};
+pub use sample::{HeadlessSample, SampleWindow, WindowedSample, HEADLESS_FRAMES};
+pub use shader::{create_shader_module, load_shader_module, spirv_words, OwnedShaderModule,
+                 SpirvProblem};
+pub use surface::{create_headless_surface, create_window_surface, headless_extension_names,
+                  headless_from_args_or_env, SurfaceBackend};
+pub use swapchain::{FormatChoiceReason, PresentModeChoice, PresentModePolicy, SurfaceFormatChoice,
+                    SharingPolicy, SurfaceFormatPolicy, SwapchainManager, SwapchainSharing};
+
+use std::rc::Rc;
 use ash::vk;
 use std::ptr;
-use std::default::Default;
-use ash::Entry;
 use ash::Instance;
-use ash::Device;
-use ash::version::{EntryV1_0, InstanceV1_0, DeviceV1_0, V1_0};
-use ash::extensions::{Swapchain, Surface, Win32Surface, XlibSurface, DebugReport};
-
-pub unsafe fn init_instance_without_extensions(app_name: &str) -> (Entry<V1_0>, Instance<V1_0>) {
-    let app_name_raw = CString::new(app_name).unwrap().as_ptr();
-
-    println!("Creating ApplicationInfo...");
-    let appinfo = vk::ApplicationInfo {
-        p_application_name: app_name_raw,
-        s_type: vk::StructureType::ApplicationInfo,
-        p_next: ptr::null(),
-        application_version: 0,
-        p_engine_name: app_name_raw,
-        engine_version: 0,
-        api_version: ash::vk_make_version!(1, 0, 36),
-    };
+use ash::version::{InstanceV1_0, DeviceV1_0, V1_0};
 
-    let pp_extension_names = Vec::<*const i8>::new();
-
-    println!("Creating InstanceCreateInfo...");
-    let create_info = vk::InstanceCreateInfo {
-        s_type: vk::StructureType::InstanceCreateInfo,
-        p_next: ptr::null(),
-        flags: Default::default(),
-        p_application_info: &appinfo,
-        pp_enabled_layer_names: ptr::null(),
-        enabled_layer_count: 0 as u32,
-        pp_enabled_extension_names: pp_extension_names.as_ptr(),
-        enabled_extension_count: pp_extension_names.len() as u32,
-    };
-
-    println!("Creating instance...");
-
-    let entry = Entry::new().unwrap();
-    let instance: Instance<V1_0> = entry
-            .create_instance(&create_info, None)
-            .expect("Instance creation error");
-    // definition of `entry` at: https://docs.rs/ash/0.20.2/src/ash/entry.rs.html#51-54
-
-    (entry, instance)
-}
-
-pub unsafe fn destroy_instance_and_panic(message: &str, instance: Instance<V1_0>) -> ! {
-    instance.destroy_instance(None);
-    panic!("panic: {}", message);
+// formats a version packed with `vk_make_version!` as major.minor.patch
+pub fn version_string(version: u32) -> String {
+    format!("{}.{}.{}", version >> 22, (version >> 12) & 0x3ff, version & 0xfff)
 }
 
 pub fn get_queue_family_supported_ops(queue_flags: vk::types::QueueFlags) -> String {
@@ -77,118 +103,63 @@ pub fn get_queue_family_supported_ops(queue_flags: vk::types::QueueFlags) -> Str
         .join(", ")
 }
 
-pub fn find_relevant_pdevice_and_queue_family(
-    instance: &Instance<V1_0>,
-    pdevices: Vec<vk::types::PhysicalDevice>,
-    required_capabilities: Vec<vk::types::QueueFlags>,
-) -> Option<(vk::types::PhysicalDevice, usize)> {
-    pdevices
-        .iter()
-        .map(|pdevice| {
-            instance
-                .get_physical_device_queue_family_properties(*pdevice)
-                .iter()
-                .enumerate()
-                .filter_map(|(index, qfp)| {
-                    let has_required_capabilities: bool = required_capabilities
-                        .iter()
-                        .all(|&req_bit| qfp.queue_flags.subset(req_bit));
-
-                    match has_required_capabilities {
-                        true => Some((*pdevice, index)),
-                        false => None,
-                    }
-                })
-                .nth(0)
-        })
-        .filter_map(|r| r)
-        .nth(0)
-}
-
-pub unsafe fn clean_up_and_panic(
-    message: &str,
-    instance: Instance<V1_0>,
-    some_ldevice: Option<Device<V1_0>>,
-    pools: Vec<vk::types::CommandPool>,
-) -> ! {
-    clean_up(instance, some_ldevice, pools);
-    panic!("panic: {}", message);
-}
-
-pub unsafe fn clean_up(
-    instance: Instance<V1_0>,
-    some_ldevice: Option<Device<V1_0>>,
-    pools: Vec<vk::CommandPool>) {
-    match some_ldevice {
-        Some(ldevice) => {
-            for pool in pools {
-                ldevice.destroy_command_pool(pool, None);
-            }
+pub fn enumerate_pdevices(instance: &Instance<V1_0>) -> Result<Vec<vk::PhysicalDevice>> {
+    let pdevices = instance
+        .enumerate_physical_devices()
+        .map_err(|result| Error::vk("enumerating physical devices", result))?;
 
-            println!("Destroying ldevice...");
-            ldevice.destroy_device(None);
-        },
-        None => {}
-    };
-
-    println!("Destroying instance...");
-    instance.destroy_instance(None);
-
-    println!("Clean up complete.");
-}
-
-#[cfg(all(windows))]
-fn get_extension_names() -> Vec<*const i8> {
-    vec![
-        Surface::name().as_ptr(),
-        Win32Surface::name().as_ptr(),
-        DebugReport::name().as_ptr(),
-    ]
+    match pdevices.len() {
+        0 => Err(Error::NoPhysicalDevices),
+        _ => Ok(pdevices),
+    }
 }
 
-#[cfg(all(unix, not(target_os = "android")))]
-fn get_extension_names() -> Vec<*const i8> {
-    vec![
-        Surface::name().as_ptr(),
-        XlibSurface::name().as_ptr(),
-        DebugReport::name().as_ptr(),
-    ]
+pub unsafe fn create_command_pool_and_buffer(
+    ldevice: &Rc<OwnedDevice>,
+    qf_index: u32,
+    command_buffer_count: u32,
+) -> Result<OwnedCommandPool> {
+    create_command_pool_with_flags(
+        ldevice,
+        qf_index,
+        vk::CommandPoolCreateFlags::empty(),
+        command_buffer_count,
+    )
 }
 
-pub unsafe fn init_instance_with_extensions(app_name: &str) -> (Entry<V1_0>, Instance<V1_0>) {
-    let app_name_raw = CString::new(app_name).unwrap().as_ptr();
-
-    println!("Creating ApplicationInfo...");
-    let appinfo = vk::ApplicationInfo {
-        p_application_name: app_name_raw,
-        s_type: vk::StructureType::ApplicationInfo,
+// e.g. `vk::COMMAND_POOL_CREATE_RESET_COMMAND_BUFFER_BIT`, for buffers that are re-recorded
+pub unsafe fn create_command_pool_with_flags(
+    ldevice: &Rc<OwnedDevice>,
+    qf_index: u32,
+    flags: vk::CommandPoolCreateFlags,
+    command_buffer_count: u32,
+) -> Result<OwnedCommandPool> {
+    let pool_create_info = vk::CommandPoolCreateInfo {
+        s_type: vk::StructureType::CommandPoolCreateInfo,
         p_next: ptr::null(),
-        application_version: 0,
-        p_engine_name: app_name_raw,
-        engine_version: 0,
-        api_version: ash::vk_make_version!(1, 0, 36),
+        flags,
+        queue_family_index: qf_index,
     };
 
-    let pp_extension_names = get_extension_names();
+    let mut command_pool = match ldevice.create_command_pool(&pool_create_info, None) {
+        Ok(command_pool) => OwnedCommandPool::new(ldevice, command_pool),
+        Err(err) => {
+            return Err(Error::vk("creating command pool", err));
+        }
+    };
 
-    println!("Creating InstanceCreateInfo...");
-    let create_info = vk::InstanceCreateInfo {
-        s_type: vk::StructureType::InstanceCreateInfo,
+    let command_buffer_allocate_info = vk::CommandBufferAllocateInfo {
+        s_type: vk::StructureType::CommandBufferAllocateInfo,
         p_next: ptr::null(),
-        flags: Default::default(),
-        p_application_info: &appinfo,
-        pp_enabled_layer_names: ptr::null(),
-        enabled_layer_count: 0 as u32,
-        pp_enabled_extension_names: pp_extension_names.as_ptr(),
-        enabled_extension_count: pp_extension_names.len() as u32,
+        command_buffer_count: command_buffer_count,
+        command_pool: command_pool.pool,
+        level: vk::CommandBufferLevel::Primary,
     };
 
-    println!("Creating instance...");
-    let entry = Entry::new().unwrap();
-    let instance: Instance<V1_0> = entry
-            .create_instance(&create_info, None)
-            .expect("Instance creation error");
-    // definition of `entry` at: https://docs.rs/ash/0.20.2/src/ash/entry.rs.html#51-54
+    // if allocation fails, `command_pool` is dropped here and the pool is destroyed with it
+    command_pool.buffers = ldevice
+        .allocate_command_buffers(&command_buffer_allocate_info)
+        .map_err(|result| Error::vk("allocating command buffers", result))?;
 
-    (entry, instance)
-}
\ No newline at end of file
+    Ok(command_pool)
+}
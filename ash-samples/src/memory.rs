@@ -0,0 +1,197 @@
+// Picking and allocating device memory for images and buffers.
+//
+// `vkGet*MemoryRequirements` says which memory types a resource may live in, as a bit per memory
+// type index; the pdevice's memory properties say what each of those types is like. The first type
+// that is allowed and has every requested property wins: Vulkan orders memory types so that earlier
+// ones are the better choice among otherwise equal candidates.
+
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+
+use ash::vk;
+use ash::version::{DeviceV1_0, InstanceV1_0};
+
+use error::{Error, Result};
+use owned::{OwnedBuffer, OwnedDevice, OwnedImage};
+
+// index of the first memory type allowed by `type_bits` that has all of `properties`
+pub fn find_memory_type_index(
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    type_bits: u32,
+    properties: vk::MemoryPropertyFlags,
+) -> Option<u32> {
+    memory_properties.memory_types[..memory_properties.memory_type_count as usize]
+        .iter()
+        .enumerate()
+        .position(|(index, memory_type)| {
+            type_bits & (1 << index) != 0 && memory_type.property_flags.subset(properties)
+        })
+        .map(|index| index as u32)
+}
+
+// `find_memory_type_index` against `device`'s pdevice
+pub fn memory_type_index(
+    device: &OwnedDevice,
+    type_bits: u32,
+    properties: vk::MemoryPropertyFlags,
+) -> Result<u32> {
+    let memory_properties = device
+        .instance
+        .get_physical_device_memory_properties(device.pdevice);
+
+    find_memory_type_index(&memory_properties, type_bits, properties).ok_or(Error::NoMemoryType {
+        type_bits,
+        properties: properties.flags(),
+    })
+}
+
+pub unsafe fn allocate(
+    device: &OwnedDevice,
+    requirements: vk::MemoryRequirements,
+    properties: vk::MemoryPropertyFlags,
+) -> Result<vk::DeviceMemory> {
+    let allocate_info = vk::MemoryAllocateInfo {
+        s_type: vk::StructureType::MemoryAllocateInfo,
+        p_next: ptr::null(),
+        allocation_size: requirements.size,
+        memory_type_index: memory_type_index(device, requirements.memory_type_bits, properties)?,
+    };
+
+    device
+        .allocate_memory(&allocate_info, None)
+        .map_err(|result| Error::vk("allocating memory", result))
+}
+
+// Gives `image` memory of its own with `properties`, bound at offset 0.
+pub unsafe fn allocate_and_bind_image(
+    image: &mut OwnedImage,
+    properties: vk::MemoryPropertyFlags,
+) -> Result<()> {
+    let device = image.device.clone();
+    // stored before binding, so it is freed with the image even if binding fails
+    image.memory = allocate(
+        &device,
+        device.get_image_memory_requirements(image.image),
+        properties,
+    )?;
+    device
+        .bind_image_memory(image.image, image.memory, 0)
+        .map_err(|result| Error::vk("binding image memory", result))
+}
+
+// Gives `buffer` memory of its own with `properties`, bound at offset 0.
+pub unsafe fn allocate_and_bind_buffer(
+    buffer: &mut OwnedBuffer,
+    properties: vk::MemoryPropertyFlags,
+) -> Result<()> {
+    let device = buffer.device.clone();
+    buffer.memory = allocate(
+        &device,
+        device.get_buffer_memory_requirements(buffer.buffer),
+        properties,
+    )?;
+    device
+        .bind_buffer_memory(buffer.buffer, buffer.memory, 0)
+        .map_err(|result| Error::vk("binding buffer memory", result))
+}
+
+// A buffer of `size` bytes used exclusively by one queue family, in memory of its own with
+// `properties`.
+pub unsafe fn create_buffer(
+    device: &Rc<OwnedDevice>,
+    size: vk::DeviceSize,
+    usage: vk::BufferUsageFlags,
+    properties: vk::MemoryPropertyFlags,
+) -> Result<OwnedBuffer> {
+    let buffer_create_info = vk::BufferCreateInfo {
+        s_type: vk::StructureType::BufferCreateInfo,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        size,
+        usage,
+        sharing_mode: vk::SharingMode::Exclusive,
+        queue_family_index_count: 0,
+        p_queue_family_indices: ptr::null(),
+    };
+
+    let mut buffer = match device.create_buffer(&buffer_create_info, None) {
+        Ok(buffer) => OwnedBuffer::new(device, buffer),
+        Err(result) => return Err(Error::vk("creating buffer", result)),
+    };
+    allocate_and_bind_buffer(&mut buffer, properties)?;
+    Ok(buffer)
+}
+
+// Copies `data` to the start of `buffer`, whose memory has to be host visible and coherent.
+pub unsafe fn write_buffer<T: Copy>(buffer: &OwnedBuffer, data: &[T]) -> Result<()> {
+    let size = mem::size_of_val(data);
+    let mapped = buffer
+        .device
+        .map_memory(buffer.memory, 0, size as vk::DeviceSize, vk::MemoryMapFlags::empty())
+        .map_err(|result| Error::vk("mapping buffer memory", result))?;
+    ptr::copy_nonoverlapping(data.as_ptr() as *const u8, mapped as *mut u8, size);
+    buffer.device.unmap_memory(buffer.memory);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_properties(types: &[vk::MemoryPropertyFlags]) -> vk::PhysicalDeviceMemoryProperties {
+        let mut memory_properties = vk::PhysicalDeviceMemoryProperties {
+            memory_type_count: types.len() as u32,
+            memory_types: [vk::MemoryType {
+                property_flags: vk::MemoryPropertyFlags::empty(),
+                heap_index: 0,
+            }; vk::VK_MAX_MEMORY_TYPES],
+            memory_heap_count: 1,
+            memory_heaps: [vk::MemoryHeap {
+                size: 1 << 30,
+                flags: vk::MemoryHeapFlags::empty(),
+            }; vk::VK_MAX_MEMORY_HEAPS],
+        };
+        for (memory_type, &property_flags) in memory_properties.memory_types.iter_mut().zip(types) {
+            memory_type.property_flags = property_flags;
+        }
+        memory_properties
+    }
+
+    #[test]
+    fn picks_the_first_allowed_type_with_all_properties() {
+        let host = vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT | vk::MEMORY_PROPERTY_HOST_COHERENT_BIT;
+        let memory_properties = memory_properties(&[
+            vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT,
+            vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT,
+            host,
+            host | vk::MEMORY_PROPERTY_HOST_CACHED_BIT,
+        ]);
+
+        assert_eq!(
+            find_memory_type_index(&memory_properties, !0, vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT),
+            Some(0)
+        );
+        assert_eq!(find_memory_type_index(&memory_properties, !0, host), Some(2));
+        // type 2 is not allowed by the requirements, so the cached one is next
+        assert_eq!(find_memory_type_index(&memory_properties, 0b1011, host), Some(3));
+    }
+
+    #[test]
+    fn ignores_types_past_the_reported_count() {
+        let mut memory_properties = memory_properties(&[
+            vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT,
+            vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT,
+        ]);
+        memory_properties.memory_type_count = 1;
+
+        assert_eq!(
+            find_memory_type_index(&memory_properties, !0, vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT),
+            None
+        );
+        assert_eq!(
+            find_memory_type_index(&memory_properties, 0, vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT),
+            None
+        );
+    }
+}
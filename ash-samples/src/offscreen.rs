@@ -0,0 +1,309 @@
+// A color image to render into instead of a swapchain image, plus the host-visible buffer its
+// contents are copied to so they can be written out as a PNG. Useful on headless machines, and for
+// comparing sample output in automated tests.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::ptr;
+use std::rc::Rc;
+use std::slice;
+
+use ash::vk;
+use ash::version::DeviceV1_0;
+use png;
+use png::HasParameters;
+
+use error::{Error, Result};
+use memory::{allocate_and_bind_image, create_buffer};
+use owned::{OwnedBuffer, OwnedDevice, OwnedImage};
+
+// tightly packed RGBA, which maps directly onto a PNG
+pub const OFFSCREEN_FORMAT: vk::Format = vk::Format::R8g8b8a8Unorm;
+const BYTES_PER_PIXEL: u64 = 4;
+
+pub struct OffscreenTarget {
+    pub extent: vk::Extent2D,
+    pub image: OwnedImage,
+    // receives the image's pixels in `record_readback`
+    pub readback: OwnedBuffer,
+}
+
+fn color_subresource_range() -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange {
+        aspect_mask: vk::IMAGE_ASPECT_COLOR_BIT,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+// how the image is accessed, and in which stage, while it is in `layout`
+fn layout_access_and_stage(layout: vk::ImageLayout) -> (vk::AccessFlags, vk::PipelineStageFlags) {
+    match layout {
+        vk::ImageLayout::TransferDstOptimal => {
+            (vk::ACCESS_TRANSFER_WRITE_BIT, vk::PIPELINE_STAGE_TRANSFER_BIT)
+        }
+        vk::ImageLayout::TransferSrcOptimal => {
+            (vk::ACCESS_TRANSFER_READ_BIT, vk::PIPELINE_STAGE_TRANSFER_BIT)
+        }
+        vk::ImageLayout::ColorAttachmentOptimal => (
+            vk::ACCESS_COLOR_ATTACHMENT_WRITE_BIT,
+            vk::PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
+        ),
+        // nothing to wait for when the old contents are discarded
+        _ => (vk::AccessFlags::empty(), vk::PIPELINE_STAGE_TOP_OF_PIPE_BIT),
+    }
+}
+
+impl OffscreenTarget {
+    pub unsafe fn new(device: &Rc<OwnedDevice>, width: u32, height: u32) -> Result<OffscreenTarget> {
+        let extent = vk::Extent2D { width, height };
+
+        let image_create_info = vk::ImageCreateInfo {
+            s_type: vk::StructureType::ImageCreateInfo,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            image_type: vk::ImageType::Type2d,
+            format: OFFSCREEN_FORMAT,
+            extent: vk::Extent3D {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples: vk::SAMPLE_COUNT_1_BIT,
+            tiling: vk::ImageTiling::Optimal,
+            usage: vk::IMAGE_USAGE_COLOR_ATTACHMENT_BIT | vk::IMAGE_USAGE_TRANSFER_SRC_BIT
+                | vk::IMAGE_USAGE_TRANSFER_DST_BIT,
+            sharing_mode: vk::SharingMode::Exclusive,
+            queue_family_index_count: 0,
+            p_queue_family_indices: ptr::null(),
+            initial_layout: vk::ImageLayout::Undefined,
+        };
+
+        let mut image = match device.create_image(&image_create_info, None) {
+            Ok(image) => OwnedImage::new(device, image),
+            Err(result) => return Err(Error::vk("creating offscreen image", result)),
+        };
+        allocate_and_bind_image(&mut image, vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT)?;
+
+        let view_create_info = vk::ImageViewCreateInfo {
+            s_type: vk::StructureType::ImageViewCreateInfo,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            view_type: vk::ImageViewType::Type2d,
+            format: OFFSCREEN_FORMAT,
+            components: vk::ComponentMapping {
+                r: vk::ComponentSwizzle::R,
+                g: vk::ComponentSwizzle::G,
+                b: vk::ComponentSwizzle::B,
+                a: vk::ComponentSwizzle::A,
+            },
+            subresource_range: color_subresource_range(),
+            image: image.image,
+        };
+        image.view = device
+            .create_image_view(&view_create_info, None)
+            .map_err(|result| Error::vk("creating offscreen image view", result))?;
+
+        // coherent, so the copied pixels are visible to the host without an explicit invalidate
+        let readback = create_buffer(
+            device,
+            width as u64 * height as u64 * BYTES_PER_PIXEL,
+            vk::BUFFER_USAGE_TRANSFER_DST_BIT,
+            vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT | vk::MEMORY_PROPERTY_HOST_COHERENT_BIT,
+        )?;
+
+        Ok(OffscreenTarget {
+            extent,
+            image,
+            readback,
+        })
+    }
+
+    // Clears the whole image to `color`, leaving it in `COLOR_ATTACHMENT_OPTIMAL` as a render pass
+    // would. Stands in for real rendering until the samples get that far.
+    pub unsafe fn record_clear(&self, command_buffer: vk::CommandBuffer, color: [f32; 4]) {
+        let device = &self.image.device;
+
+        self.record_layout_transition(
+            command_buffer,
+            vk::ImageLayout::Undefined,
+            vk::ImageLayout::TransferDstOptimal,
+        );
+        device.cmd_clear_color_image(
+            command_buffer,
+            self.image.image,
+            vk::ImageLayout::TransferDstOptimal,
+            &vk::ClearColorValue::new_float32(color),
+            &[color_subresource_range()],
+        );
+        self.record_layout_transition(
+            command_buffer,
+            vk::ImageLayout::TransferDstOptimal,
+            vk::ImageLayout::ColorAttachmentOptimal,
+        );
+    }
+
+    // Copies the image, which must be in `COLOR_ATTACHMENT_OPTIMAL`, into `readback`. Once the
+    // command buffer has finished executing, `read_pixels` returns the result.
+    pub unsafe fn record_readback(&self, command_buffer: vk::CommandBuffer) {
+        let device = &self.image.device;
+
+        self.record_layout_transition(
+            command_buffer,
+            vk::ImageLayout::ColorAttachmentOptimal,
+            vk::ImageLayout::TransferSrcOptimal,
+        );
+
+        let region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            // 0 means tightly packed
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::IMAGE_ASPECT_COLOR_BIT,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+            image_extent: vk::Extent3D {
+                width: self.extent.width,
+                height: self.extent.height,
+                depth: 1,
+            },
+        };
+        device.cmd_copy_image_to_buffer(
+            command_buffer,
+            self.image.image,
+            vk::ImageLayout::TransferSrcOptimal,
+            self.readback.buffer,
+            &[region],
+        );
+
+        // make the copy visible to the host once the submission's fence has signalled
+        let buffer_barrier = vk::BufferMemoryBarrier {
+            s_type: vk::StructureType::BufferMemoryBarrier,
+            p_next: ptr::null(),
+            src_access_mask: vk::ACCESS_TRANSFER_WRITE_BIT,
+            dst_access_mask: vk::ACCESS_HOST_READ_BIT,
+            src_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED,
+            buffer: self.readback.buffer,
+            offset: 0,
+            size: vk::VK_WHOLE_SIZE,
+        };
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PIPELINE_STAGE_TRANSFER_BIT,
+            vk::PIPELINE_STAGE_HOST_BIT,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[buffer_barrier],
+            &[],
+        );
+    }
+
+    unsafe fn record_layout_transition(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+    ) {
+        let (src_access_mask, src_stage_mask) = layout_access_and_stage(old_layout);
+        let (dst_access_mask, dst_stage_mask) = layout_access_and_stage(new_layout);
+        let barrier = vk::ImageMemoryBarrier {
+            s_type: vk::StructureType::ImageMemoryBarrier,
+            p_next: ptr::null(),
+            src_access_mask,
+            dst_access_mask,
+            old_layout,
+            new_layout,
+            src_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED,
+            image: self.image.image,
+            subresource_range: color_subresource_range(),
+        };
+        self.image.device.cmd_pipeline_barrier(
+            command_buffer,
+            src_stage_mask,
+            dst_stage_mask,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier],
+        );
+    }
+
+    // RGBA rows, top to bottom. Only valid after a submission with `record_readback` has completed.
+    pub unsafe fn read_pixels(&self) -> Result<Vec<u8>> {
+        let device = &self.readback.device;
+        let size = self.extent.width as u64 * self.extent.height as u64 * BYTES_PER_PIXEL;
+
+        let mapped = device
+            .map_memory(self.readback.memory, 0, size, vk::MemoryMapFlags::empty())
+            .map_err(|result| Error::vk("mapping readback buffer", result))?;
+        let pixels = slice::from_raw_parts(mapped as *const u8, size as usize).to_vec();
+        device.unmap_memory(self.readback.memory);
+
+        Ok(pixels)
+    }
+
+    pub unsafe fn write_png<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let pixels = self.read_pixels()?;
+        write_png(path, self.extent.width, self.extent.height, &pixels)
+    }
+}
+
+// Writes tightly packed 8-bit RGBA `pixels` to `path`.
+pub fn write_png<P: AsRef<Path>>(path: P, width: u32, height: u32, pixels: &[u8]) -> Result<()> {
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    Ok(())
+}
+
+// Submits `command_buffer` to `queue` and blocks until it has finished executing.
+pub unsafe fn submit_and_wait(
+    device: &OwnedDevice,
+    queue: vk::Queue,
+    command_buffer: vk::CommandBuffer,
+) -> Result<()> {
+    let fence_create_info = vk::FenceCreateInfo {
+        s_type: vk::StructureType::FenceCreateInfo,
+        p_next: ptr::null(),
+        flags: vk::FenceCreateFlags::empty(),
+    };
+    let fence = device
+        .create_fence(&fence_create_info, None)
+        .map_err(|result| Error::vk("creating fence", result))?;
+
+    let submit_info = vk::SubmitInfo {
+        s_type: vk::StructureType::SubmitInfo,
+        p_next: ptr::null(),
+        wait_semaphore_count: 0,
+        p_wait_semaphores: ptr::null(),
+        p_wait_dst_stage_mask: ptr::null(),
+        command_buffer_count: 1,
+        p_command_buffers: &command_buffer,
+        signal_semaphore_count: 0,
+        p_signal_semaphores: ptr::null(),
+    };
+
+    let result = device
+        .queue_submit(queue, &[submit_info], fence)
+        .map_err(|result| Error::vk("submitting command buffer", result))
+        .and_then(|_| {
+            device
+                .wait_for_fences(&[fence], true, u64::max_value())
+                .map_err(|result| Error::vk("waiting for fence", result))
+        });
+    device.destroy_fence(fence, None);
+    result
+}
@@ -0,0 +1,396 @@
+// Owned wrappers around the Vulkan handles the samples create.
+//
+// Every child holds an `Rc` to its parent, so a parent can never be destroyed while one of its
+// children is still alive: the instance outlives every device and surface, a device outlives every
+// command pool and swapchain created from it. Dropping a wrapper destroys its handle; early returns
+// and panics therefore unwind through the right teardown order without any bookkeeping.
+
+use std::ffi::{CStr, CString};
+use std::ops::Deref;
+use std::rc::Rc;
+
+use ash::vk;
+use ash::Entry;
+use ash::Instance;
+use ash::Device;
+use ash::version::{DeviceV1_0, InstanceV1_0, V1_0};
+use ash::extensions::{Surface, Swapchain};
+
+pub struct OwnedInstance {
+    // the entry holds the loaded Vulkan library, so it has to outlive the instance
+    pub entry: Entry<V1_0>,
+    instance: Instance<V1_0>,
+    pub enabled_extensions: Vec<CString>,
+}
+
+impl OwnedInstance {
+    pub fn new(
+        entry: Entry<V1_0>,
+        instance: Instance<V1_0>,
+        enabled_extensions: Vec<CString>,
+    ) -> Rc<OwnedInstance> {
+        Rc::new(OwnedInstance {
+            entry,
+            instance,
+            enabled_extensions,
+        })
+    }
+
+    pub fn has_extension(&self, name: &CStr) -> bool {
+        self.enabled_extensions
+            .iter()
+            .any(|extension| extension.as_c_str() == name)
+    }
+}
+
+impl Deref for OwnedInstance {
+    type Target = Instance<V1_0>;
+
+    fn deref(&self) -> &Instance<V1_0> {
+        &self.instance
+    }
+}
+
+impl Drop for OwnedInstance {
+    fn drop(&mut self) {
+        info!("Destroying instance...");
+        unsafe {
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+pub struct OwnedDevice {
+    pub instance: Rc<OwnedInstance>,
+    pub pdevice: vk::PhysicalDevice,
+    device: Device<V1_0>,
+    pub enabled_features: vk::PhysicalDeviceFeatures,
+    pub enabled_extensions: Vec<CString>,
+}
+
+impl OwnedDevice {
+    pub fn new(
+        instance: &Rc<OwnedInstance>,
+        pdevice: vk::PhysicalDevice,
+        device: Device<V1_0>,
+        enabled_features: vk::PhysicalDeviceFeatures,
+        enabled_extensions: Vec<CString>,
+    ) -> Rc<OwnedDevice> {
+        Rc::new(OwnedDevice {
+            instance: instance.clone(),
+            pdevice,
+            device,
+            enabled_features,
+            enabled_extensions,
+        })
+    }
+
+    pub fn has_extension(&self, name: &CStr) -> bool {
+        self.enabled_extensions
+            .iter()
+            .any(|extension| extension.as_c_str() == name)
+    }
+}
+
+impl Deref for OwnedDevice {
+    type Target = Device<V1_0>;
+
+    fn deref(&self) -> &Device<V1_0> {
+        &self.device
+    }
+}
+
+impl Drop for OwnedDevice {
+    fn drop(&mut self) {
+        info!("Destroying ldevice...");
+        unsafe {
+            // nothing may still be executing on the device's queues when it is destroyed
+            let _ = self.device.device_wait_idle();
+            self.device.destroy_device(None);
+        }
+    }
+}
+
+pub struct OwnedCommandPool {
+    pub device: Rc<OwnedDevice>,
+    pub pool: vk::CommandPool,
+    pub buffers: Vec<vk::CommandBuffer>,
+}
+
+impl OwnedCommandPool {
+    pub fn new(device: &Rc<OwnedDevice>, pool: vk::CommandPool) -> OwnedCommandPool {
+        OwnedCommandPool {
+            device: device.clone(),
+            pool,
+            buffers: Vec::new(),
+        }
+    }
+}
+
+impl Drop for OwnedCommandPool {
+    fn drop(&mut self) {
+        unsafe {
+            // command buffers are freed implicitly when their pool is destroyed
+            self.device.destroy_command_pool(self.pool, None);
+        }
+    }
+}
+
+// An image, the memory bound to it and a view of it. `memory` and `view` start out null and are
+// filled in as they are created, so whatever exists is cleaned up if a later step fails.
+pub struct OwnedImage {
+    pub device: Rc<OwnedDevice>,
+    pub image: vk::Image,
+    pub memory: vk::DeviceMemory,
+    pub view: vk::ImageView,
+}
+
+impl OwnedImage {
+    pub fn new(device: &Rc<OwnedDevice>, image: vk::Image) -> OwnedImage {
+        OwnedImage {
+            device: device.clone(),
+            image,
+            memory: vk::DeviceMemory::null(),
+            view: vk::ImageView::null(),
+        }
+    }
+}
+
+impl Drop for OwnedImage {
+    fn drop(&mut self) {
+        unsafe {
+            // destroying or freeing a null handle is a no-op
+            self.device.destroy_image_view(self.view, None);
+            self.device.destroy_image(self.image, None);
+            self.device.free_memory(self.memory, None);
+        }
+    }
+}
+
+// A buffer and the memory bound to it; `memory` starts out null, as for `OwnedImage`.
+pub struct OwnedBuffer {
+    pub device: Rc<OwnedDevice>,
+    pub buffer: vk::Buffer,
+    pub memory: vk::DeviceMemory,
+}
+
+impl OwnedBuffer {
+    pub fn new(device: &Rc<OwnedDevice>, buffer: vk::Buffer) -> OwnedBuffer {
+        OwnedBuffer {
+            device: device.clone(),
+            buffer,
+            memory: vk::DeviceMemory::null(),
+        }
+    }
+}
+
+impl Drop for OwnedBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_buffer(self.buffer, None);
+            self.device.free_memory(self.memory, None);
+        }
+    }
+}
+
+// Shared, since every pipeline layout built from it keeps it alive.
+pub struct OwnedDescriptorSetLayout {
+    pub device: Rc<OwnedDevice>,
+    pub layout: vk::DescriptorSetLayout,
+}
+
+impl OwnedDescriptorSetLayout {
+    pub fn new(
+        device: &Rc<OwnedDevice>,
+        layout: vk::DescriptorSetLayout,
+    ) -> Rc<OwnedDescriptorSetLayout> {
+        Rc::new(OwnedDescriptorSetLayout {
+            device: device.clone(),
+            layout,
+        })
+    }
+}
+
+impl Drop for OwnedDescriptorSetLayout {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_descriptor_set_layout(self.layout, None);
+        }
+    }
+}
+
+pub struct OwnedPipelineLayout {
+    pub device: Rc<OwnedDevice>,
+    pub set_layouts: Vec<Rc<OwnedDescriptorSetLayout>>,
+    pub layout: vk::PipelineLayout,
+}
+
+impl OwnedPipelineLayout {
+    pub fn new(
+        device: &Rc<OwnedDevice>,
+        set_layouts: &[Rc<OwnedDescriptorSetLayout>],
+        layout: vk::PipelineLayout,
+    ) -> Rc<OwnedPipelineLayout> {
+        Rc::new(OwnedPipelineLayout {
+            device: device.clone(),
+            set_layouts: set_layouts.to_vec(),
+            layout,
+        })
+    }
+}
+
+impl Drop for OwnedPipelineLayout {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_pipeline_layout(self.layout, None);
+        }
+    }
+}
+
+pub struct OwnedDescriptorPool {
+    pub device: Rc<OwnedDevice>,
+    pub pool: vk::DescriptorPool,
+    pub sets: Vec<vk::DescriptorSet>,
+}
+
+impl OwnedDescriptorPool {
+    pub fn new(device: &Rc<OwnedDevice>, pool: vk::DescriptorPool) -> OwnedDescriptorPool {
+        OwnedDescriptorPool {
+            device: device.clone(),
+            pool,
+            sets: Vec::new(),
+        }
+    }
+}
+
+impl Drop for OwnedDescriptorPool {
+    fn drop(&mut self) {
+        unsafe {
+            // descriptor sets are freed implicitly when their pool is destroyed
+            self.device.destroy_descriptor_pool(self.pool, None);
+        }
+    }
+}
+
+// Shared, since every framebuffer created against it keeps it alive.
+pub struct OwnedRenderPass {
+    pub device: Rc<OwnedDevice>,
+    pub render_pass: vk::RenderPass,
+}
+
+impl OwnedRenderPass {
+    pub fn new(device: &Rc<OwnedDevice>, render_pass: vk::RenderPass) -> Rc<OwnedRenderPass> {
+        Rc::new(OwnedRenderPass {
+            device: device.clone(),
+            render_pass,
+        })
+    }
+}
+
+impl Drop for OwnedRenderPass {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_render_pass(self.render_pass, None);
+        }
+    }
+}
+
+// The views a framebuffer is created from must outlive it, but are owned elsewhere; whoever holds
+// the framebuffer drops it first.
+pub struct OwnedFramebuffer {
+    pub device: Rc<OwnedDevice>,
+    pub render_pass: Rc<OwnedRenderPass>,
+    pub framebuffer: vk::Framebuffer,
+}
+
+impl OwnedFramebuffer {
+    pub fn new(
+        device: &Rc<OwnedDevice>,
+        render_pass: &Rc<OwnedRenderPass>,
+        framebuffer: vk::Framebuffer,
+    ) -> OwnedFramebuffer {
+        OwnedFramebuffer {
+            device: device.clone(),
+            render_pass: render_pass.clone(),
+            framebuffer,
+        }
+    }
+}
+
+impl Drop for OwnedFramebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_framebuffer(self.framebuffer, None);
+        }
+    }
+}
+
+pub struct OwnedSurface {
+    pub instance: Rc<OwnedInstance>,
+    pub loader: Surface,
+    pub surface: vk::SurfaceKHR,
+}
+
+impl OwnedSurface {
+    pub fn new(
+        instance: &Rc<OwnedInstance>,
+        loader: Surface,
+        surface: vk::SurfaceKHR,
+    ) -> Rc<OwnedSurface> {
+        Rc::new(OwnedSurface {
+            instance: instance.clone(),
+            loader,
+            surface,
+        })
+    }
+}
+
+impl Drop for OwnedSurface {
+    fn drop(&mut self) {
+        info!("Destroying surface...");
+        unsafe {
+            self.loader.destroy_surface_khr(self.surface, None);
+        }
+    }
+}
+
+pub struct OwnedSwapchain {
+    pub device: Rc<OwnedDevice>,
+    // the surface a swapchain presents to must outlive the swapchain
+    pub surface: Rc<OwnedSurface>,
+    pub loader: Swapchain,
+    pub swapchain: vk::SwapchainKHR,
+    pub images: Vec<vk::Image>,
+    pub image_views: Vec<vk::ImageView>,
+}
+
+impl OwnedSwapchain {
+    pub fn new(
+        device: &Rc<OwnedDevice>,
+        surface: &Rc<OwnedSurface>,
+        loader: Swapchain,
+        swapchain: vk::SwapchainKHR,
+    ) -> OwnedSwapchain {
+        OwnedSwapchain {
+            device: device.clone(),
+            surface: surface.clone(),
+            loader,
+            swapchain,
+            images: Vec::new(),
+            image_views: Vec::new(),
+        }
+    }
+}
+
+impl Drop for OwnedSwapchain {
+    fn drop(&mut self) {
+        info!("Destroying swapchain...");
+        unsafe {
+            for &view in self.image_views.iter() {
+                self.device.destroy_image_view(view, None);
+            }
+            // the images themselves belong to the swapchain and go away with it
+            self.loader.destroy_swapchain_khr(self.swapchain, None);
+        }
+    }
+}
@@ -0,0 +1,418 @@
+// Ranking of physical devices, so samples do not just take whichever one the driver lists first.
+
+use std::env;
+use std::ffi::{CStr, CString};
+
+use ash::vk;
+
+use error::{Error, Result};
+use features;
+use query::PdeviceQuery;
+
+pub trait PdeviceScorer<Q: PdeviceQuery> {
+    // higher is better; `None` means `pdevice` cannot be used at all
+    fn score(&self, query: &Q, pdevice: Q::Pdevice) -> Option<u64>;
+}
+
+// Prefers discrete over integrated over virtual over CPU devices, then more device-local memory.
+// Devices missing any of the required features or device extensions are rejected.
+pub struct DefaultScorer {
+    pub required_features: vk::PhysicalDeviceFeatures,
+    pub required_extensions: Vec<CString>,
+}
+
+impl DefaultScorer {
+    pub fn new() -> DefaultScorer {
+        DefaultScorer {
+            required_features: features::no_features(),
+            required_extensions: Vec::new(),
+        }
+    }
+
+    pub fn require_features(mut self, required_features: vk::PhysicalDeviceFeatures) -> DefaultScorer {
+        self.required_features = required_features;
+        self
+    }
+
+    pub fn require_extension(mut self, name: &CStr) -> DefaultScorer {
+        self.required_extensions.push(name.to_owned());
+        self
+    }
+}
+
+impl<Q: PdeviceQuery> PdeviceScorer<Q> for DefaultScorer {
+    fn score(&self, query: &Q, pdevice: Q::Pdevice) -> Option<u64> {
+        let supported_features = query.features(pdevice);
+        if !features::missing_features(&self.required_features, &supported_features).is_empty() {
+            return None;
+        }
+
+        let supported_extensions = match query.device_extensions(pdevice) {
+            Ok(extensions) => extensions,
+            Err(_) => return None,
+        };
+        if !self.required_extensions.iter().all(|name| supported_extensions.contains(name)) {
+            return None;
+        }
+
+        let type_rank: u64 = match query.properties(pdevice).device_type {
+            vk::PhysicalDeviceType::DiscreteGpu => 4,
+            vk::PhysicalDeviceType::IntegratedGpu => 3,
+            vk::PhysicalDeviceType::VirtualGpu => 2,
+            vk::PhysicalDeviceType::Cpu => 1,
+            _ => 0,
+        };
+
+        let memory_properties = query.memory_properties(pdevice);
+        let device_local_mib: u64 = memory_properties.memory_heaps
+            [..memory_properties.memory_heap_count as usize]
+            .iter()
+            .filter(|heap| heap.flags.subset(vk::MEMORY_HEAP_DEVICE_LOCAL_BIT))
+            .map(|heap| heap.size as u64 >> 20)
+            .sum();
+
+        // the device type always dominates; memory only breaks ties between devices of one type
+        Some((type_rank << 48) + device_local_mib.min((1 << 48) - 1))
+    }
+}
+
+// set to `index:<n>`, `name:<text>`, `0x<vendor>` or `0x<vendor>:0x<device>` to force a
+// particular pdevice; a selector without a prefix is a name
+pub const DEVICE_VAR: &str = "ASH_SAMPLES_DEVICE";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PdeviceSelector {
+    // position in the list returned by `enumerate_physical_devices`
+    Index(usize),
+    // case-insensitive substring of the device name
+    Name(String),
+    // PCI vendor ID, and optionally device ID
+    Ids {
+        vendor_id: u32,
+        device_id: Option<u32>,
+    },
+}
+
+impl PdeviceSelector {
+    // Every form but names has a prefix, so names like "GTX 1080" or "ad:be" are never mistaken for
+    // an index or IDs. IDs are hexadecimal and always written with `0x`.
+    pub fn parse(spec: &str) -> Result<PdeviceSelector> {
+        let invalid = || Error::InvalidDeviceSelector(String::from(spec));
+
+        if spec.starts_with("index:") {
+            return spec["index:".len()..]
+                .parse::<usize>()
+                .map(PdeviceSelector::Index)
+                .map_err(|_| invalid());
+        }
+        if spec.starts_with("name:") {
+            return Ok(PdeviceSelector::Name(spec["name:".len()..].to_lowercase()));
+        }
+        if spec.starts_with("0x") {
+            let mut ids = spec.splitn(2, ':').map(parse_hex);
+            return match (ids.next(), ids.next()) {
+                (Some(Some(vendor_id)), None) => Ok(PdeviceSelector::Ids {
+                    vendor_id,
+                    device_id: None,
+                }),
+                (Some(Some(vendor_id)), Some(Some(device_id))) => Ok(PdeviceSelector::Ids {
+                    vendor_id,
+                    device_id: Some(device_id),
+                }),
+                _ => Err(invalid()),
+            };
+        }
+        Ok(PdeviceSelector::Name(spec.to_lowercase()))
+    }
+
+    // `--device <spec>` or `--device=<spec>` on the command line wins over `ASH_SAMPLES_DEVICE`
+    pub fn from_args_or_env() -> Result<Option<PdeviceSelector>> {
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--device" {
+                if let Some(spec) = args.next() {
+                    return PdeviceSelector::parse(&spec).map(Some);
+                }
+            } else if arg.starts_with("--device=") {
+                return PdeviceSelector::parse(&arg["--device=".len()..]).map(Some);
+            }
+        }
+
+        match env::var(DEVICE_VAR) {
+            Ok(spec) => PdeviceSelector::parse(&spec).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn matches(&self, index: usize, properties: &vk::PhysicalDeviceProperties) -> bool {
+        match *self {
+            PdeviceSelector::Index(selected) => index == selected,
+            PdeviceSelector::Name(ref name) => unsafe {
+                CStr::from_ptr(properties.device_name.as_ptr())
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .contains(name.as_str())
+            },
+            PdeviceSelector::Ids {
+                vendor_id,
+                device_id,
+            } => {
+                properties.vendor_id == vendor_id
+                    && device_id.map_or(true, |device_id| properties.device_id == device_id)
+            }
+        }
+    }
+}
+
+// `0x` followed by at least one hex digit
+fn parse_hex(id: &str) -> Option<u32> {
+    match id.starts_with("0x") && id.len() > 2 {
+        true => u32::from_str_radix(&id[2..], 16).ok(),
+        false => None,
+    }
+}
+
+// Orders `pdevices` best first, dropping the ones `scorer` rejects. If `selector` is given, only the
+// devices it matches are considered.
+pub fn rank_pdevices<Q: PdeviceQuery>(
+    query: &Q,
+    pdevices: Vec<Q::Pdevice>,
+    scorer: &PdeviceScorer<Q>,
+    selector: Option<&PdeviceSelector>,
+) -> Result<Vec<Q::Pdevice>> {
+    let mut candidates: Vec<(u64, Q::Pdevice)> = Vec::new();
+    let mut selected_any = false;
+
+    for (index, &pdevice) in pdevices.iter().enumerate() {
+        let properties = query.properties(pdevice);
+        if let Some(selector) = selector {
+            if !selector.matches(index, &properties) {
+                continue;
+            }
+        }
+        selected_any = true;
+
+        let name = unsafe { CStr::from_ptr(properties.device_name.as_ptr()) };
+        match scorer.score(query, pdevice) {
+            Some(score) => {
+                info!("pdevice {} ({}): score {}", index, name.to_string_lossy(), score);
+                candidates.push((score, pdevice));
+            }
+            None => info!("pdevice {} ({}): unsuitable", index, name.to_string_lossy()),
+        }
+    }
+
+    if !selected_any {
+        return Err(match selector {
+            Some(selector) => Error::NoMatchingPdevice(format!("{:?}", selector)),
+            None => Error::NoPhysicalDevices,
+        });
+    }
+    if candidates.is_empty() {
+        return Err(Error::NoSuitablePdevice);
+    }
+
+    // stable, so equally scored devices keep the driver's order
+    candidates.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(candidates.into_iter().map(|(_, pdevice)| pdevice).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::mem;
+
+    use ash::vk;
+
+    use super::*;
+    use testing::{properties, FakePdevice, FakeQuery};
+
+    #[test]
+    fn parses_prefixed_indices() {
+        assert_eq!(PdeviceSelector::parse("index:1").unwrap(), PdeviceSelector::Index(1));
+        match PdeviceSelector::parse("index:first") {
+            Err(Error::InvalidDeviceSelector(spec)) => assert_eq!(spec, "index:first"),
+            other => panic!("expected InvalidDeviceSelector, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_names_with_or_without_prefix() {
+        assert_eq!(
+            PdeviceSelector::parse("name:GeForce").unwrap(),
+            PdeviceSelector::Name(String::from("geforce"))
+        );
+        // neither is taken for an index or IDs without the matching prefix
+        assert_eq!(
+            PdeviceSelector::parse("1080").unwrap(),
+            PdeviceSelector::Name(String::from("1080"))
+        );
+        assert_eq!(
+            PdeviceSelector::parse("ad:be").unwrap(),
+            PdeviceSelector::Name(String::from("ad:be"))
+        );
+        assert_eq!(
+            PdeviceSelector::parse("name:0x10de").unwrap(),
+            PdeviceSelector::Name(String::from("0x10de"))
+        );
+    }
+
+    #[test]
+    fn parses_hex_ids() {
+        assert_eq!(
+            PdeviceSelector::parse("0x10de").unwrap(),
+            PdeviceSelector::Ids {
+                vendor_id: 0x10de,
+                device_id: None,
+            }
+        );
+        assert_eq!(
+            PdeviceSelector::parse("0x10de:0x1b80").unwrap(),
+            PdeviceSelector::Ids {
+                vendor_id: 0x10de,
+                device_id: Some(0x1b80),
+            }
+        );
+        for spec in ["0x", "0xgpu", "0x10de:1b80", "0x10de:"].iter() {
+            match PdeviceSelector::parse(spec) {
+                Err(Error::InvalidDeviceSelector(_)) => (),
+                other => panic!("expected InvalidDeviceSelector for {}, got {:?}", spec, other),
+            }
+        }
+    }
+
+    #[test]
+    fn matches_by_index() {
+        let properties = properties("GeForce GTX 1080", 0x10de, 0x1b80);
+        assert!(PdeviceSelector::Index(1).matches(1, &properties));
+        assert!(!PdeviceSelector::Index(1).matches(0, &properties));
+    }
+
+    #[test]
+    fn matches_names_case_insensitively() {
+        let properties = properties("GeForce GTX 1080", 0x10de, 0x1b80);
+        assert!(PdeviceSelector::parse("gtx").unwrap().matches(0, &properties));
+        assert!(PdeviceSelector::parse("name:GTX 1080").unwrap().matches(0, &properties));
+        assert!(PdeviceSelector::parse("1080").unwrap().matches(0, &properties));
+        assert!(!PdeviceSelector::parse("radeon").unwrap().matches(0, &properties));
+    }
+
+    #[test]
+    fn matches_by_ids() {
+        let properties = properties("GeForce GTX 1080", 0x10de, 0x1b80);
+        // vendor only
+        assert!(PdeviceSelector::parse("0x10de").unwrap().matches(0, &properties));
+        assert!(!PdeviceSelector::parse("0x1002").unwrap().matches(0, &properties));
+        assert!(PdeviceSelector::parse("0x10de:0x1b80").unwrap().matches(0, &properties));
+        assert!(!PdeviceSelector::parse("0x10de:0x1b81").unwrap().matches(0, &properties));
+    }
+
+    fn fake(
+        name: &str,
+        device_type: vk::PhysicalDeviceType,
+        device_local_mib: u64,
+        extensions: &[&str],
+    ) -> FakePdevice {
+        let mut properties = properties(name, 0x10de, 0x1b80);
+        properties.device_type = device_type;
+
+        // plain old data; unused heaps and memory types stay zeroed
+        let mut memory_properties: vk::PhysicalDeviceMemoryProperties = unsafe { mem::zeroed() };
+        memory_properties.memory_heap_count = 2;
+        memory_properties.memory_heaps[0] = vk::MemoryHeap {
+            size: device_local_mib << 20,
+            flags: vk::MEMORY_HEAP_DEVICE_LOCAL_BIT,
+        };
+        // host memory, which never counts
+        memory_properties.memory_heaps[1] = vk::MemoryHeap {
+            size: 64 << 30,
+            flags: vk::MemoryHeapFlags::empty(),
+        };
+
+        let mut pdevice = FakePdevice::new(Vec::new(), Vec::new());
+        pdevice.properties = properties;
+        pdevice.memory_properties = memory_properties;
+        pdevice.extensions = extensions.iter().map(|&name| CString::new(name).unwrap()).collect();
+        pdevice
+    }
+
+    #[test]
+    fn ranks_discrete_above_integrated() {
+        let query = FakeQuery {
+            pdevices: vec![
+                fake("Integrated", vk::PhysicalDeviceType::IntegratedGpu, 8192, &[]),
+                fake("Discrete", vk::PhysicalDeviceType::DiscreteGpu, 2048, &[]),
+                fake("Software", vk::PhysicalDeviceType::Cpu, 16384, &[]),
+            ],
+        };
+
+        let ranked = rank_pdevices(&query, query.handles(), &DefaultScorer::new(), None).unwrap();
+        assert_eq!(ranked, vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn device_local_memory_breaks_ties() {
+        let query = FakeQuery {
+            pdevices: vec![
+                fake("Small", vk::PhysicalDeviceType::DiscreteGpu, 4096, &[]),
+                fake("Large", vk::PhysicalDeviceType::DiscreteGpu, 8192, &[]),
+                fake("Small too", vk::PhysicalDeviceType::DiscreteGpu, 4096, &[]),
+            ],
+        };
+
+        // equal scores keep the driver's order
+        let ranked = rank_pdevices(&query, query.handles(), &DefaultScorer::new(), None).unwrap();
+        assert_eq!(ranked, vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn rejects_missing_features_or_extensions() {
+        let mut required = features::no_features();
+        required.geometry_shader = 1;
+        let scorer = DefaultScorer::new()
+            .require_features(required)
+            .require_extension(&CString::new("VK_KHR_swapchain").unwrap());
+
+        let discrete = vk::PhysicalDeviceType::DiscreteGpu;
+        let swapchain = "VK_KHR_swapchain";
+        let maintenance = "VK_KHR_maintenance1";
+
+        let no_feature = fake("No feature", discrete, 8192, &[swapchain]);
+        let mut no_extension = fake("No extension", discrete, 8192, &[maintenance]);
+        no_extension.features.geometry_shader = 1;
+        let integrated = vk::PhysicalDeviceType::IntegratedGpu;
+        let mut both = fake("Both", integrated, 1024, &[maintenance, swapchain]);
+        both.features.geometry_shader = 1;
+
+        let query = FakeQuery {
+            pdevices: vec![no_feature, no_extension, both],
+        };
+        assert_eq!(rank_pdevices(&query, query.handles(), &scorer, None).unwrap(), vec![2]);
+
+        match rank_pdevices(&query, vec![0, 1], &scorer, None) {
+            Err(Error::NoSuitablePdevice) => (),
+            other => panic!("expected NoSuitablePdevice, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn selector_limits_the_candidates() {
+        let query = FakeQuery {
+            pdevices: vec![
+                fake("GeForce GTX 1080", vk::PhysicalDeviceType::DiscreteGpu, 8192, &[]),
+                fake("Intel HD Graphics 630", vk::PhysicalDeviceType::IntegratedGpu, 1024, &[]),
+            ],
+        };
+        let scorer = DefaultScorer::new();
+
+        let selector = PdeviceSelector::parse("intel").unwrap();
+        let ranked = rank_pdevices(&query, query.handles(), &scorer, Some(&selector)).unwrap();
+        assert_eq!(ranked, vec![1]);
+
+        let selector = PdeviceSelector::parse("radeon").unwrap();
+        match rank_pdevices(&query, query.handles(), &scorer, Some(&selector)) {
+            Err(Error::NoMatchingPdevice(_)) => (),
+            other => panic!("expected NoMatchingPdevice, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,323 @@
+// The pdevice queries that device and queue family selection is built on, behind a trait so the
+// selection logic can run against an in-memory fake as well as a live instance.
+
+use std::ffi::{CStr, CString};
+
+use ash::vk;
+use ash::Instance;
+use ash::version::{InstanceV1_0, V1_0};
+
+use error::{Error, Result};
+use get_queue_family_supported_ops;
+use owned::OwnedSurface;
+
+pub trait PdeviceQuery {
+    // `vk::PhysicalDevice` for a live instance; fakes can use anything they like
+    type Pdevice: Copy;
+
+    fn queue_family_properties(&self, pdevice: Self::Pdevice) -> Vec<vk::QueueFamilyProperties>;
+    // whether family `family_index` can present to the surface being targeted, if any
+    fn surface_support(&self, pdevice: Self::Pdevice, family_index: u32) -> bool;
+    fn properties(&self, pdevice: Self::Pdevice) -> vk::PhysicalDeviceProperties;
+    fn features(&self, pdevice: Self::Pdevice) -> vk::PhysicalDeviceFeatures;
+    fn memory_properties(&self, pdevice: Self::Pdevice) -> vk::PhysicalDeviceMemoryProperties;
+    // names of the device extensions the pdevice supports
+    fn device_extensions(&self, pdevice: Self::Pdevice) -> Result<Vec<CString>>;
+}
+
+// Answers queries from a live instance. Without a surface, no family supports presentation.
+pub struct InstanceQuery<'a> {
+    instance: &'a Instance<V1_0>,
+    surface: Option<&'a OwnedSurface>,
+}
+
+impl<'a> InstanceQuery<'a> {
+    pub fn new(instance: &'a Instance<V1_0>) -> InstanceQuery<'a> {
+        InstanceQuery {
+            instance,
+            surface: None,
+        }
+    }
+
+    pub fn with_surface(mut self, surface: &'a OwnedSurface) -> InstanceQuery<'a> {
+        self.surface = Some(surface);
+        self
+    }
+}
+
+impl<'a> PdeviceQuery for InstanceQuery<'a> {
+    type Pdevice = vk::PhysicalDevice;
+
+    fn queue_family_properties(&self, pdevice: vk::PhysicalDevice) -> Vec<vk::QueueFamilyProperties> {
+        self.instance.get_physical_device_queue_family_properties(pdevice)
+    }
+
+    fn surface_support(&self, pdevice: vk::PhysicalDevice, family_index: u32) -> bool {
+        match self.surface {
+            Some(surface) => surface.loader.get_physical_device_surface_support_khr(
+                pdevice,
+                family_index,
+                surface.surface,
+            ),
+            None => false,
+        }
+    }
+
+    fn properties(&self, pdevice: vk::PhysicalDevice) -> vk::PhysicalDeviceProperties {
+        self.instance.get_physical_device_properties(pdevice)
+    }
+
+    fn features(&self, pdevice: vk::PhysicalDevice) -> vk::PhysicalDeviceFeatures {
+        self.instance.get_physical_device_features(pdevice)
+    }
+
+    fn memory_properties(
+        &self,
+        pdevice: vk::PhysicalDevice,
+    ) -> vk::PhysicalDeviceMemoryProperties {
+        self.instance.get_physical_device_memory_properties(pdevice)
+    }
+
+    fn device_extensions(&self, pdevice: vk::PhysicalDevice) -> Result<Vec<CString>> {
+        let properties = self.instance
+            .enumerate_device_extension_properties(pdevice)
+            .map_err(|result| Error::vk("enumerating device extensions", result))?;
+        Ok(properties
+            .iter()
+            .map(|extension| {
+                unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) }.to_owned()
+            })
+            .collect())
+    }
+}
+
+// First pdevice, in the given order, with a queue family that has all `required_capabilities`;
+// returns that pdevice and the index of its first such family.
+pub fn find_relevant_pdevice_and_queue_family<Q: PdeviceQuery>(
+    query: &Q,
+    pdevices: Vec<Q::Pdevice>,
+    required_capabilities: Vec<vk::QueueFlags>,
+) -> Result<(Q::Pdevice, usize)> {
+    pdevices
+        .iter()
+        .map(|&pdevice| {
+            query
+                .queue_family_properties(pdevice)
+                .iter()
+                .enumerate()
+                .filter_map(|(index, qfp)| {
+                    let has_required_capabilities: bool = required_capabilities
+                        .iter()
+                        .all(|&req_bit| qfp.queue_flags.subset(req_bit));
+
+                    match has_required_capabilities {
+                        true => Some((pdevice, index)),
+                        false => None,
+                    }
+                })
+                .nth(0)
+        })
+        .filter_map(|r| r)
+        .nth(0)
+        .ok_or_else(|| Error::NoQueueFamily {
+            required: get_queue_family_supported_ops(
+                required_capabilities
+                    .iter()
+                    .fold(vk::QueueFlags::empty(), |acc, &bit| acc | bit),
+            ),
+        })
+}
+
+// First pdevice, in the given order, with both a graphics family and a family that can present;
+// returns that pdevice and the two family indices. A family that can do both is preferred, so the
+// sample needs a single queue and no ownership transfers.
+pub fn find_pdevice_with_queue_family_supporting_graphics_and_presentation<Q: PdeviceQuery>(
+    query: &Q,
+    pdevices: Vec<Q::Pdevice>,
+) -> Result<(Q::Pdevice, u32, u32)> {
+    for &pd in pdevices.iter() {
+        // both families have to come from the same pdevice
+        let mut graphics_qf_index: Option<u32> = None;
+        let mut presentation_qf_index: Option<u32> = None;
+
+        for (index, qfp) in query.queue_family_properties(pd).iter().enumerate() {
+            let index: u32 = index as u32;
+            let graphics = qfp.queue_flags.subset(vk::QUEUE_GRAPHICS_BIT);
+            let presentation = query.surface_support(pd, index);
+
+            if graphics && presentation {
+                return Ok((pd, index, index));
+            }
+
+            if graphics_qf_index.is_none() && graphics {
+                graphics_qf_index = Some(index);
+            }
+
+            if presentation_qf_index.is_none() && presentation {
+                presentation_qf_index = Some(index);
+            }
+        }
+
+        if let (Some(graphics), Some(presentation)) = (graphics_qf_index, presentation_qf_index) {
+            return Ok((pd, graphics, presentation));
+        }
+    }
+
+    Err(Error::NoQueueFamily {
+        required: String::from("GRAPHICS, PRESENTATION"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use super::*;
+    use testing::{family, FakePdevice, FakeQuery};
+
+    #[test]
+    fn picks_first_family_with_all_capabilities() {
+        let query = FakeQuery {
+            pdevices: vec![FakePdevice::new(
+                vec![
+                    family(vk::QUEUE_TRANSFER_BIT, 2),
+                    family(vk::QUEUE_COMPUTE_BIT | vk::QUEUE_TRANSFER_BIT, 4),
+                    family(vk::QUEUE_GRAPHICS_BIT | vk::QUEUE_COMPUTE_BIT, 16),
+                ],
+                vec![],
+            )],
+        };
+
+        let found = find_relevant_pdevice_and_queue_family(
+            &query,
+            query.handles(),
+            vec![vk::QUEUE_COMPUTE_BIT],
+        ).unwrap();
+        assert_eq!(found, (0, 1));
+
+        let found = find_relevant_pdevice_and_queue_family(
+            &query,
+            query.handles(),
+            vec![vk::QUEUE_GRAPHICS_BIT, vk::QUEUE_COMPUTE_BIT],
+        ).unwrap();
+        assert_eq!(found, (0, 2));
+    }
+
+    #[test]
+    fn skips_pdevices_without_a_capable_family() {
+        let query = FakeQuery {
+            pdevices: vec![
+                FakePdevice::new(vec![family(vk::QUEUE_COMPUTE_BIT, 1)], vec![]),
+                FakePdevice::new(vec![family(vk::QUEUE_GRAPHICS_BIT, 1)], vec![]),
+                FakePdevice::new(vec![family(vk::QUEUE_GRAPHICS_BIT, 1)], vec![]),
+            ],
+        };
+
+        let found = find_relevant_pdevice_and_queue_family(
+            &query,
+            query.handles(),
+            vec![vk::QUEUE_GRAPHICS_BIT],
+        ).unwrap();
+        assert_eq!(found, (1, 0));
+
+        // the caller's order decides, e.g. after ranking
+        let found = find_relevant_pdevice_and_queue_family(
+            &query,
+            vec![2, 1, 0],
+            vec![vk::QUEUE_GRAPHICS_BIT],
+        ).unwrap();
+        assert_eq!(found, (2, 0));
+    }
+
+    #[test]
+    fn reports_missing_capabilities() {
+        let query = FakeQuery {
+            pdevices: vec![FakePdevice::new(vec![family(vk::QUEUE_TRANSFER_BIT, 1)], vec![])],
+        };
+
+        match find_relevant_pdevice_and_queue_family(
+            &query,
+            query.handles(),
+            vec![vk::QUEUE_GRAPHICS_BIT, vk::QUEUE_COMPUTE_BIT],
+        ) {
+            Err(Error::NoQueueFamily { required }) => assert_eq!(required, "GRAPHICS, COMPUTE"),
+            other => panic!("expected NoQueueFamily, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn graphics_and_presentation_from_one_family() {
+        let query = FakeQuery {
+            pdevices: vec![FakePdevice::new(
+                vec![family(vk::QUEUE_GRAPHICS_BIT, 1), family(vk::QUEUE_COMPUTE_BIT, 1)],
+                vec![true, true],
+            )],
+        };
+
+        let found =
+            find_pdevice_with_queue_family_supporting_graphics_and_presentation(&query, query.handles())
+                .unwrap();
+        assert_eq!(found, (0, 0, 0));
+    }
+
+    #[test]
+    fn graphics_and_presentation_from_separate_families() {
+        let query = FakeQuery {
+            pdevices: vec![FakePdevice::new(
+                vec![
+                    family(vk::QUEUE_TRANSFER_BIT, 1),
+                    family(vk::QUEUE_GRAPHICS_BIT, 1),
+                    family(vk::QUEUE_COMPUTE_BIT, 1),
+                ],
+                vec![true, false, true],
+            )],
+        };
+
+        let found =
+            find_pdevice_with_queue_family_supporting_graphics_and_presentation(&query, query.handles())
+                .unwrap();
+        assert_eq!(found, (0, 1, 0));
+    }
+
+    #[test]
+    fn graphics_and_presentation_prefer_one_family() {
+        let query = FakeQuery {
+            pdevices: vec![FakePdevice::new(
+                vec![
+                    family(vk::QUEUE_GRAPHICS_BIT, 1),
+                    family(vk::QUEUE_COMPUTE_BIT, 1),
+                    family(vk::QUEUE_GRAPHICS_BIT | vk::QUEUE_COMPUTE_BIT, 1),
+                ],
+                vec![false, true, true],
+            )],
+        };
+
+        let found =
+            find_pdevice_with_queue_family_supporting_graphics_and_presentation(&query, query.handles())
+                .unwrap();
+        assert_eq!(found, (0, 2, 2));
+    }
+
+    #[test]
+    fn graphics_and_presentation_never_mix_pdevices() {
+        let query = FakeQuery {
+            pdevices: vec![
+                // graphics but no presentation
+                FakePdevice::new(vec![family(vk::QUEUE_GRAPHICS_BIT, 1)], vec![false]),
+                // presentation but no graphics
+                FakePdevice::new(vec![family(vk::QUEUE_COMPUTE_BIT, 1)], vec![true]),
+                FakePdevice::new(vec![family(vk::QUEUE_GRAPHICS_BIT, 1)], vec![true]),
+            ],
+        };
+
+        let found =
+            find_pdevice_with_queue_family_supporting_graphics_and_presentation(&query, query.handles())
+                .unwrap();
+        assert_eq!(found, (2, 0, 0));
+
+        match find_pdevice_with_queue_family_supporting_graphics_and_presentation(&query, vec![0, 1]) {
+            Err(Error::NoQueueFamily { .. }) => (),
+            other => panic!("expected NoQueueFamily, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,323 @@
+// Picks queue families for a set of roles and turns the result into a valid list of
+// `vk::DeviceQueueCreateInfo`s: one per distinct family, as Vulkan requires.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ptr;
+
+use ash::vk;
+use ash::Device;
+use ash::Instance;
+use ash::version::{DeviceV1_0, V1_0};
+
+use error::{Error, Result};
+use owned::OwnedSurface;
+use query::{InstanceQuery, PdeviceQuery};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum QueueRole {
+    Graphics,
+    Present,
+    Compute,
+    // a transfer queue on a family without graphics or compute, if the device has one
+    Transfer,
+    SparseBinding,
+}
+
+impl QueueRole {
+    fn name(&self) -> &'static str {
+        match *self {
+            QueueRole::Graphics => "GRAPHICS",
+            QueueRole::Present => "PRESENTATION",
+            QueueRole::Compute => "COMPUTE",
+            QueueRole::Transfer => "TRANSFER",
+            QueueRole::SparseBinding => "SPARSE",
+        }
+    }
+}
+
+struct FamilyPlan {
+    index: u32,
+    // one entry per queue created in this family
+    priorities: Vec<f32>,
+}
+
+pub struct QueuePlan {
+    families: Vec<FamilyPlan>,
+    // role -> (queue family index, queue index within that family)
+    assignments: BTreeMap<QueueRole, (u32, u32)>,
+}
+
+impl QueuePlan {
+    // `present_support[i]` says whether family `i` can present to the target surface; it is only
+    // consulted for `QueueRole::Present`.
+    pub fn new(
+        family_properties: &[vk::QueueFamilyProperties],
+        present_support: &[bool],
+        roles: &[(QueueRole, f32)],
+    ) -> Result<QueuePlan> {
+        let mut roles = roles.to_vec();
+        // graphics is planned before present, so present can share its family and queue
+        roles.sort_by(|a, b| a.0.cmp(&b.0));
+        roles.dedup_by(|a, b| a.0 == b.0);
+
+        let mut plan = QueuePlan {
+            families: Vec::new(),
+            assignments: BTreeMap::new(),
+        };
+
+        for &(role, priority) in roles.iter() {
+            let family = match plan.pick_family(family_properties, present_support, role) {
+                Some(family) => family,
+                None => {
+                    return Err(Error::NoQueueFamily {
+                        required: String::from(role.name()),
+                    })
+                }
+            };
+
+            // presenting from the graphics queue is the common case, and costs no extra queue
+            if role == QueueRole::Present {
+                if let Some(&(graphics_family, graphics_queue)) =
+                    plan.assignments.get(&QueueRole::Graphics)
+                {
+                    if graphics_family == family {
+                        plan.assignments.insert(role, (family, graphics_queue));
+                        continue;
+                    }
+                }
+            }
+
+            let queue_count = family_properties[family as usize].queue_count as usize;
+            let position = match plan.families.iter().position(|f| f.index == family) {
+                Some(position) => position,
+                None => {
+                    plan.families.push(FamilyPlan {
+                        index: family,
+                        priorities: Vec::new(),
+                    });
+                    plan.families.len() - 1
+                }
+            };
+
+            let family_plan = &mut plan.families[position];
+            if family_plan.priorities.len() < queue_count {
+                family_plan.priorities.push(priority);
+            } else {
+                // out of queues in this family: share the last one, at the higher of both priorities
+                let last = family_plan.priorities.last_mut().unwrap();
+                *last = (*last).max(priority);
+            }
+            let queue_index = family_plan.priorities.len() as u32 - 1;
+            plan.assignments.insert(role, (family, queue_index));
+        }
+
+        Ok(plan)
+    }
+
+    // Plans queues on `pdevice`; `surface` is only needed if `roles` contains `QueueRole::Present`.
+    pub fn for_pdevice(
+        instance: &Instance<V1_0>,
+        pdevice: vk::PhysicalDevice,
+        surface: Option<&OwnedSurface>,
+        roles: &[(QueueRole, f32)],
+    ) -> Result<QueuePlan> {
+        let query = match surface {
+            Some(surface) => InstanceQuery::new(instance).with_surface(surface),
+            None => InstanceQuery::new(instance),
+        };
+        QueuePlan::from_query(&query, pdevice, roles)
+    }
+
+    pub fn from_query<Q: PdeviceQuery>(
+        query: &Q,
+        pdevice: Q::Pdevice,
+        roles: &[(QueueRole, f32)],
+    ) -> Result<QueuePlan> {
+        let family_properties = query.queue_family_properties(pdevice);
+        let present_support: Vec<bool> = (0..family_properties.len() as u32)
+            .map(|index| query.surface_support(pdevice, index))
+            .collect();
+
+        QueuePlan::new(&family_properties, &present_support, roles)
+    }
+
+    fn pick_family(
+        &self,
+        family_properties: &[vk::QueueFamilyProperties],
+        present_support: &[bool],
+        role: QueueRole,
+    ) -> Option<u32> {
+        let find = |predicate: &Fn(usize, vk::QueueFlags) -> bool| {
+            family_properties
+                .iter()
+                .enumerate()
+                .position(|(index, qfp)| qfp.queue_count > 0 && predicate(index, qfp.queue_flags))
+                .map(|index| index as u32)
+        };
+
+        match role {
+            QueueRole::Graphics => find(&|_, flags| flags.subset(vk::QUEUE_GRAPHICS_BIT)),
+            QueueRole::Present => {
+                let supports_present =
+                    |index: usize| present_support.get(index).cloned().unwrap_or(false);
+                match self.assignments.get(&QueueRole::Graphics) {
+                    Some(&(graphics_family, _)) if supports_present(graphics_family as usize) => {
+                        Some(graphics_family)
+                    }
+                    _ => find(&|index, _| supports_present(index)),
+                }
+            }
+            QueueRole::Compute => find(&|_, flags| {
+                flags.subset(vk::QUEUE_COMPUTE_BIT) && !flags.subset(vk::QUEUE_GRAPHICS_BIT)
+            }).or_else(|| find(&|_, flags| flags.subset(vk::QUEUE_COMPUTE_BIT))),
+            QueueRole::Transfer => {
+                let dedicated = find(&|_, flags| {
+                    flags.subset(vk::QUEUE_TRANSFER_BIT) && !flags.subset(vk::QUEUE_GRAPHICS_BIT)
+                        && !flags.subset(vk::QUEUE_COMPUTE_BIT)
+                });
+                let non_graphics = || {
+                    find(&|_, flags| {
+                        flags.subset(vk::QUEUE_TRANSFER_BIT) && !flags.subset(vk::QUEUE_GRAPHICS_BIT)
+                    })
+                };
+                // graphics and compute queues always support transfers, even if they don't say so
+                let any = || {
+                    find(&|_, flags| {
+                        flags.intersects(
+                            vk::QUEUE_TRANSFER_BIT | vk::QUEUE_GRAPHICS_BIT | vk::QUEUE_COMPUTE_BIT,
+                        )
+                    })
+                };
+                dedicated.or_else(non_graphics).or_else(any)
+            }
+            QueueRole::SparseBinding => {
+                // prefer a family we already create queues in
+                let used: Vec<u32> = self.families.iter().map(|f| f.index).collect();
+                find(&|index, flags| {
+                    flags.subset(vk::QUEUE_SPARSE_BINDING_BIT) && used.contains(&(index as u32))
+                }).or_else(|| find(&|_, flags| flags.subset(vk::QUEUE_SPARSE_BINDING_BIT)))
+            }
+        }
+    }
+
+    pub fn family_index(&self, role: QueueRole) -> Option<u32> {
+        self.assignments.get(&role).map(|&(family, _)| family)
+    }
+
+    // distinct family indices the plan creates queues in
+    pub fn family_indices(&self) -> Vec<u32> {
+        self.families.iter().map(|f| f.index).collect()
+    }
+
+    // The returned structs point into `self`, which must outlive the call to `create_device`.
+    pub fn create_infos(&self) -> Vec<vk::DeviceQueueCreateInfo> {
+        self.families
+            .iter()
+            .map(|family| vk::DeviceQueueCreateInfo {
+                s_type: vk::StructureType::DeviceQueueCreateInfo,
+                p_next: ptr::null(),
+                flags: Default::default(),
+                queue_family_index: family.index,
+                p_queue_priorities: family.priorities.as_ptr(),
+                queue_count: family.priorities.len() as u32,
+            })
+            .collect()
+    }
+
+    pub unsafe fn get_queues(&self, device: &Device<V1_0>) -> HashMap<QueueRole, vk::Queue> {
+        self.assignments
+            .iter()
+            .map(|(&role, &(family, index))| (role, device.get_device_queue(family, index)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use super::*;
+    use testing::family;
+
+    fn all_flags() -> vk::QueueFlags {
+        vk::QUEUE_GRAPHICS_BIT | vk::QUEUE_COMPUTE_BIT | vk::QUEUE_TRANSFER_BIT
+    }
+
+    #[test]
+    fn present_shares_the_graphics_queue() {
+        let families = [family(vk::QUEUE_COMPUTE_BIT, 1), family(all_flags(), 16)];
+        let plan = QueuePlan::new(
+            &families,
+            &[true, true],
+            &[(QueueRole::Present, 0.5), (QueueRole::Graphics, 1.0)],
+        ).unwrap();
+
+        // the graphics family is preferred even though an earlier family can present
+        assert_eq!(plan.assignments[&QueueRole::Graphics], (1, 0));
+        assert_eq!(plan.assignments[&QueueRole::Present], (1, 0));
+        assert_eq!(plan.family_indices(), vec![1]);
+        assert_eq!(plan.families[0].priorities, vec![1.0]);
+    }
+
+    #[test]
+    fn present_gets_its_own_family_if_graphics_cannot_present() {
+        let families = [family(all_flags(), 16), family(vk::QUEUE_TRANSFER_BIT, 1)];
+        let plan = QueuePlan::new(
+            &families,
+            &[false, true],
+            &[(QueueRole::Graphics, 1.0), (QueueRole::Present, 1.0)],
+        ).unwrap();
+
+        assert_eq!(plan.family_index(QueueRole::Graphics), Some(0));
+        assert_eq!(plan.family_index(QueueRole::Present), Some(1));
+        assert_eq!(plan.family_indices(), vec![0, 1]);
+    }
+
+    #[test]
+    fn roles_share_the_last_queue_when_a_family_runs_out() {
+        let roles = [(QueueRole::Graphics, 0.5), (QueueRole::Compute, 1.0)];
+
+        let plan = QueuePlan::new(&[family(all_flags(), 2)], &[], &roles).unwrap();
+        assert_eq!(plan.assignments[&QueueRole::Graphics], (0, 0));
+        assert_eq!(plan.assignments[&QueueRole::Compute], (0, 1));
+        assert_eq!(plan.families[0].priorities, vec![0.5, 1.0]);
+
+        // a single queue is shared, at the higher of the two priorities
+        let plan = QueuePlan::new(&[family(all_flags(), 1)], &[], &roles).unwrap();
+        assert_eq!(plan.assignments[&QueueRole::Graphics], (0, 0));
+        assert_eq!(plan.assignments[&QueueRole::Compute], (0, 0));
+        assert_eq!(plan.families[0].priorities, vec![1.0]);
+    }
+
+    #[test]
+    fn transfer_prefers_a_transfer_only_family() {
+        let roles = [(QueueRole::Transfer, 1.0)];
+
+        let families = [
+            family(all_flags(), 16),
+            family(vk::QUEUE_COMPUTE_BIT | vk::QUEUE_TRANSFER_BIT, 8),
+            family(vk::QUEUE_TRANSFER_BIT, 2),
+        ];
+        let plan = QueuePlan::new(&families, &[], &roles).unwrap();
+        assert_eq!(plan.family_index(QueueRole::Transfer), Some(2));
+
+        // then any family without graphics
+        let plan = QueuePlan::new(&families[..2], &[], &roles).unwrap();
+        assert_eq!(plan.family_index(QueueRole::Transfer), Some(1));
+
+        // then graphics or compute families, which support transfers without saying so
+        let families = [family(vk::QUEUE_GRAPHICS_BIT | vk::QUEUE_COMPUTE_BIT, 16)];
+        let plan = QueuePlan::new(&families, &[], &roles).unwrap();
+        assert_eq!(plan.family_index(QueueRole::Transfer), Some(0));
+    }
+
+    #[test]
+    fn reports_missing_graphics_family() {
+        let families = [family(vk::QUEUE_COMPUTE_BIT | vk::QUEUE_TRANSFER_BIT, 4)];
+        match QueuePlan::new(&families, &[true], &[(QueueRole::Graphics, 1.0)]) {
+            Err(Error::NoQueueFamily { required }) => assert_eq!(required, "GRAPHICS"),
+            Err(error) => panic!("expected NoQueueFamily, got {:?}", error),
+            Ok(_) => panic!("expected NoQueueFamily, got a plan"),
+        }
+    }
+}
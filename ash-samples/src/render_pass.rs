@@ -0,0 +1,137 @@
+// The render pass the samples draw with: a single subpass writing a swapchain color image and,
+// optionally, a depth buffer.
+//
+// Both attachments are cleared on load, since every frame redraws everything. Color is stored and
+// left in `PRESENT_SRC_KHR`, ready to be presented without another barrier, as `FrameLoop` expects.
+// Depth is only needed while the subpass runs, so it is not stored.
+
+use std::ptr;
+use std::rc::Rc;
+
+use ash::vk;
+use ash::version::DeviceV1_0;
+
+use error::{Error, Result};
+use owned::{OwnedDevice, OwnedRenderPass};
+
+pub const COLOR_ATTACHMENT: u32 = 0;
+pub const DEPTH_ATTACHMENT: u32 = 1;
+
+fn color_attachment(format: vk::Format) -> vk::AttachmentDescription {
+    vk::AttachmentDescription {
+        flags: vk::AttachmentDescriptionFlags::empty(),
+        format,
+        samples: vk::SAMPLE_COUNT_1_BIT,
+        load_op: vk::AttachmentLoadOp::Clear,
+        store_op: vk::AttachmentStoreOp::Store,
+        stencil_load_op: vk::AttachmentLoadOp::DontCare,
+        stencil_store_op: vk::AttachmentStoreOp::DontCare,
+        // the previous contents are cleared anyway
+        initial_layout: vk::ImageLayout::Undefined,
+        final_layout: vk::ImageLayout::PresentSrcKhr,
+    }
+}
+
+fn depth_attachment(format: vk::Format) -> vk::AttachmentDescription {
+    vk::AttachmentDescription {
+        flags: vk::AttachmentDescriptionFlags::empty(),
+        format,
+        samples: vk::SAMPLE_COUNT_1_BIT,
+        load_op: vk::AttachmentLoadOp::Clear,
+        store_op: vk::AttachmentStoreOp::DontCare,
+        stencil_load_op: vk::AttachmentLoadOp::DontCare,
+        stencil_store_op: vk::AttachmentStoreOp::DontCare,
+        initial_layout: vk::ImageLayout::Undefined,
+        final_layout: vk::ImageLayout::DepthStencilAttachmentOptimal,
+    }
+}
+
+// Orders the subpass after whatever used the attachments before it: the presentation engine
+// reading the color image, which the acquire semaphore waits for at the color output stage, and the
+// previous frame's depth tests, since all frames in flight share one depth buffer.
+fn external_dependency(with_depth: bool) -> vk::SubpassDependency {
+    let (src_stage_mask, dst_stage_mask, src_access_mask, dst_access_mask) = match with_depth {
+        true => (
+            vk::PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT
+                | vk::PIPELINE_STAGE_LATE_FRAGMENT_TESTS_BIT,
+            vk::PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT
+                | vk::PIPELINE_STAGE_EARLY_FRAGMENT_TESTS_BIT,
+            vk::ACCESS_DEPTH_STENCIL_ATTACHMENT_WRITE_BIT,
+            vk::ACCESS_COLOR_ATTACHMENT_WRITE_BIT
+                | vk::ACCESS_DEPTH_STENCIL_ATTACHMENT_READ_BIT
+                | vk::ACCESS_DEPTH_STENCIL_ATTACHMENT_WRITE_BIT,
+        ),
+        false => (
+            vk::PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
+            vk::PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
+            vk::AccessFlags::empty(),
+            vk::ACCESS_COLOR_ATTACHMENT_WRITE_BIT,
+        ),
+    };
+
+    vk::SubpassDependency {
+        src_subpass: vk::VK_SUBPASS_EXTERNAL,
+        dst_subpass: 0,
+        src_stage_mask,
+        dst_stage_mask,
+        src_access_mask,
+        dst_access_mask,
+        dependency_flags: vk::DependencyFlags::empty(),
+    }
+}
+
+// A render pass with the color attachment at `COLOR_ATTACHMENT` and, if `depth_format` is given,
+// a depth attachment at `DEPTH_ATTACHMENT`. Framebuffers for it list their views in that order.
+pub unsafe fn create_render_pass(
+    device: &Rc<OwnedDevice>,
+    color_format: vk::Format,
+    depth_format: Option<vk::Format>,
+) -> Result<Rc<OwnedRenderPass>> {
+    let mut attachments = vec![color_attachment(color_format)];
+    if let Some(depth_format) = depth_format {
+        attachments.push(depth_attachment(depth_format));
+    }
+
+    let color_reference = vk::AttachmentReference {
+        attachment: COLOR_ATTACHMENT,
+        layout: vk::ImageLayout::ColorAttachmentOptimal,
+    };
+    let depth_reference = vk::AttachmentReference {
+        attachment: DEPTH_ATTACHMENT,
+        layout: vk::ImageLayout::DepthStencilAttachmentOptimal,
+    };
+
+    let subpass = vk::SubpassDescription {
+        flags: Default::default(),
+        pipeline_bind_point: vk::PipelineBindPoint::Graphics,
+        input_attachment_count: 0,
+        p_input_attachments: ptr::null(),
+        color_attachment_count: 1,
+        p_color_attachments: &color_reference,
+        p_resolve_attachments: ptr::null(),
+        p_depth_stencil_attachment: match depth_format {
+            Some(_) => &depth_reference,
+            None => ptr::null(),
+        },
+        preserve_attachment_count: 0,
+        p_preserve_attachments: ptr::null(),
+    };
+    let dependency = external_dependency(depth_format.is_some());
+
+    let create_info = vk::RenderPassCreateInfo {
+        s_type: vk::StructureType::RenderPassCreateInfo,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        attachment_count: attachments.len() as u32,
+        p_attachments: attachments.as_ptr(),
+        subpass_count: 1,
+        p_subpasses: &subpass,
+        dependency_count: 1,
+        p_dependencies: &dependency,
+    };
+
+    device
+        .create_render_pass(&create_info, None)
+        .map(|render_pass| OwnedRenderPass::new(device, render_pass))
+        .map_err(|result| Error::vk("creating render pass", result))
+}
@@ -0,0 +1,405 @@
+// Gathers everything a physical device reports about itself into a `serde_json::Value`, which can
+// then be written out as JSON or as indented text.
+
+use std::ffi::CStr;
+use std::fmt::Write;
+
+use ash::vk;
+use ash::Instance;
+use ash::version::{InstanceV1_0, V1_0};
+use serde_json::{Map, Value};
+
+use error::{Error, Result};
+use features;
+use {get_queue_family_supported_ops, version_string};
+
+macro_rules! fields_to_json {
+    ($source:expr, $($field:ident),*) => {{
+        let mut map = Map::new();
+        $(
+            map.insert(String::from(stringify!($field)), json!($source.$field));
+        )*
+        map
+    }};
+}
+
+macro_rules! flag_fields_to_json {
+    ($map:expr, $source:expr, $($field:ident),*) => {
+        $(
+            $map.insert(String::from(stringify!($field)), json!($source.$field.flags()));
+        )*
+    };
+}
+
+fn flag_names<F: Copy>(flags: F, names: &[(&'static str, F)], contains: fn(F, F) -> bool) -> Vec<&'static str> {
+    names
+        .iter()
+        .filter(|&&(_, bit)| contains(flags, bit))
+        .map(|&(name, _)| name)
+        .collect()
+}
+
+fn memory_property_names(flags: vk::MemoryPropertyFlags) -> Vec<&'static str> {
+    flag_names(
+        flags,
+        &[
+            ("DEVICE_LOCAL", vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT),
+            ("HOST_VISIBLE", vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT),
+            ("HOST_COHERENT", vk::MEMORY_PROPERTY_HOST_COHERENT_BIT),
+            ("HOST_CACHED", vk::MEMORY_PROPERTY_HOST_CACHED_BIT),
+            ("LAZILY_ALLOCATED", vk::MEMORY_PROPERTY_LAZILY_ALLOCATED_BIT),
+        ],
+        |flags, bit| flags.subset(bit),
+    )
+}
+
+fn memory_heap_names(flags: vk::MemoryHeapFlags) -> Vec<&'static str> {
+    flag_names(
+        flags,
+        &[("DEVICE_LOCAL", vk::MEMORY_HEAP_DEVICE_LOCAL_BIT)],
+        |flags, bit| flags.subset(bit),
+    )
+}
+
+fn properties_json(properties: &vk::PhysicalDeviceProperties) -> Value {
+    let limits = &properties.limits;
+    let mut limits_json = fields_to_json!(
+        limits,
+        max_image_dimension1_d,
+        max_image_dimension2_d,
+        max_image_dimension3_d,
+        max_image_dimension_cube,
+        max_image_array_layers,
+        max_texel_buffer_elements,
+        max_uniform_buffer_range,
+        max_storage_buffer_range,
+        max_push_constants_size,
+        max_memory_allocation_count,
+        max_sampler_allocation_count,
+        buffer_image_granularity,
+        sparse_address_space_size,
+        max_bound_descriptor_sets,
+        max_per_stage_descriptor_samplers,
+        max_per_stage_descriptor_uniform_buffers,
+        max_per_stage_descriptor_storage_buffers,
+        max_per_stage_descriptor_sampled_images,
+        max_per_stage_descriptor_storage_images,
+        max_per_stage_descriptor_input_attachments,
+        max_per_stage_resources,
+        max_descriptor_set_samplers,
+        max_descriptor_set_uniform_buffers,
+        max_descriptor_set_uniform_buffers_dynamic,
+        max_descriptor_set_storage_buffers,
+        max_descriptor_set_storage_buffers_dynamic,
+        max_descriptor_set_sampled_images,
+        max_descriptor_set_storage_images,
+        max_descriptor_set_input_attachments,
+        max_vertex_input_attributes,
+        max_vertex_input_bindings,
+        max_vertex_input_attribute_offset,
+        max_vertex_input_binding_stride,
+        max_vertex_output_components,
+        max_tessellation_generation_level,
+        max_tessellation_patch_size,
+        max_tessellation_control_per_vertex_input_components,
+        max_tessellation_control_per_vertex_output_components,
+        max_tessellation_control_per_patch_output_components,
+        max_tessellation_control_total_output_components,
+        max_tessellation_evaluation_input_components,
+        max_tessellation_evaluation_output_components,
+        max_geometry_shader_invocations,
+        max_geometry_input_components,
+        max_geometry_output_components,
+        max_geometry_output_vertices,
+        max_geometry_total_output_components,
+        max_fragment_input_components,
+        max_fragment_output_attachments,
+        max_fragment_dual_src_attachments,
+        max_fragment_combined_output_resources,
+        max_compute_shared_memory_size,
+        max_compute_work_group_count,
+        max_compute_work_group_invocations,
+        max_compute_work_group_size,
+        sub_pixel_precision_bits,
+        sub_texel_precision_bits,
+        mipmap_precision_bits,
+        max_draw_indexed_index_value,
+        max_draw_indirect_count,
+        max_sampler_lod_bias,
+        max_sampler_anisotropy,
+        max_viewports,
+        max_viewport_dimensions,
+        viewport_bounds_range,
+        viewport_sub_pixel_bits,
+        min_memory_map_alignment,
+        min_texel_buffer_offset_alignment,
+        min_uniform_buffer_offset_alignment,
+        min_storage_buffer_offset_alignment,
+        min_texel_offset,
+        max_texel_offset,
+        min_texel_gather_offset,
+        max_texel_gather_offset,
+        min_interpolation_offset,
+        max_interpolation_offset,
+        sub_pixel_interpolation_offset_bits,
+        max_framebuffer_width,
+        max_framebuffer_height,
+        max_framebuffer_layers,
+        max_color_attachments,
+        max_sample_mask_words,
+        timestamp_compute_and_graphics,
+        timestamp_period,
+        max_clip_distances,
+        max_cull_distances,
+        max_combined_clip_and_cull_distances,
+        discrete_queue_priorities,
+        point_size_range,
+        line_width_range,
+        point_size_granularity,
+        line_width_granularity,
+        strict_lines,
+        standard_sample_locations,
+        optimal_buffer_copy_offset_alignment,
+        optimal_buffer_copy_row_pitch_alignment,
+        non_coherent_atom_size
+    );
+    // sample counts are bitmasks of `vk::SAMPLE_COUNT_*_BIT`
+    flag_fields_to_json!(
+        limits_json,
+        limits,
+        framebuffer_color_sample_counts,
+        framebuffer_depth_sample_counts,
+        framebuffer_stencil_sample_counts,
+        framebuffer_no_attachments_sample_counts,
+        sampled_image_color_sample_counts,
+        sampled_image_integer_sample_counts,
+        sampled_image_depth_sample_counts,
+        sampled_image_stencil_sample_counts,
+        storage_image_sample_counts
+    );
+
+    let sparse = fields_to_json!(
+        properties.sparse_properties,
+        residency_standard2_d_block_shape,
+        residency_standard2_d_multisample_block_shape,
+        residency_standard3_d_block_shape,
+        residency_aligned_mip_size,
+        residency_non_resident_strict
+    );
+
+    let uuid = properties
+        .pipeline_cache_uuid
+        .iter()
+        .fold(String::new(), |mut uuid, byte| {
+            let _ = write!(uuid, "{:02x}", byte);
+            uuid
+        });
+
+    json!({
+        "device_name": unsafe { CStr::from_ptr(properties.device_name.as_ptr()) }.to_string_lossy(),
+        "device_type": format!("{:?}", properties.device_type),
+        "api_version": version_string(properties.api_version),
+        "driver_version": properties.driver_version,
+        "vendor_id": format!("0x{:04x}", properties.vendor_id),
+        "device_id": format!("0x{:04x}", properties.device_id),
+        "pipeline_cache_uuid": uuid,
+        "limits": Value::Object(limits_json),
+        "sparse_properties": Value::Object(sparse),
+    })
+}
+
+fn memory_json(memory: &vk::PhysicalDeviceMemoryProperties) -> Value {
+    let heaps: Vec<Value> = memory.memory_heaps[..memory.memory_heap_count as usize]
+        .iter()
+        .map(|heap| {
+            json!({
+                "size": heap.size,
+                "flags": memory_heap_names(heap.flags),
+            })
+        })
+        .collect();
+
+    let types: Vec<Value> = memory.memory_types[..memory.memory_type_count as usize]
+        .iter()
+        .map(|memory_type| {
+            json!({
+                "heap_index": memory_type.heap_index,
+                "property_flags": memory_property_names(memory_type.property_flags),
+            })
+        })
+        .collect();
+
+    json!({
+        "heaps": heaps,
+        "types": types,
+    })
+}
+
+pub fn pdevice_report(instance: &Instance<V1_0>, pdevice: vk::PhysicalDevice) -> Result<Value> {
+    let properties = instance.get_physical_device_properties(pdevice);
+    let memory = instance.get_physical_device_memory_properties(pdevice);
+
+    let queue_families: Vec<Value> = instance
+        .get_physical_device_queue_family_properties(pdevice)
+        .iter()
+        .enumerate()
+        .map(|(index, qfp)| {
+            json!({
+                "index": index,
+                "queue_count": qfp.queue_count,
+                "supported_operations": get_queue_family_supported_ops(qfp.queue_flags),
+                "timestamp_valid_bits": qfp.timestamp_valid_bits,
+                "min_image_transfer_granularity": [
+                    qfp.min_image_transfer_granularity.width,
+                    qfp.min_image_transfer_granularity.height,
+                    qfp.min_image_transfer_granularity.depth,
+                ],
+            })
+        })
+        .collect();
+
+    let mut features_json = Map::new();
+    for (name, enabled) in features::feature_values(&instance.get_physical_device_features(pdevice)) {
+        features_json.insert(String::from(name), json!(enabled));
+    }
+
+    let extensions: Vec<Value> = instance
+        .enumerate_device_extension_properties(pdevice)
+        .map_err(|result| Error::vk("enumerating device extensions", result))?
+        .iter()
+        .map(|extension| {
+            json!({
+                "name": unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) }.to_string_lossy(),
+                "spec_version": extension.spec_version,
+            })
+        })
+        .collect();
+
+    Ok(json!({
+        "properties": properties_json(&properties),
+        "memory": memory_json(&memory),
+        "queue_families": queue_families,
+        "features": Value::Object(features_json),
+        "extensions": extensions,
+    }))
+}
+
+// Renders a report as indented `key: value` lines, in the same order as the JSON output.
+pub fn format_report(report: &Value) -> String {
+    let mut out = String::new();
+    format_value(&mut out, report, 0);
+    out
+}
+
+fn format_value(out: &mut String, value: &Value, indent: usize) {
+    match *value {
+        Value::Object(ref map) => {
+            for (key, value) in map.iter() {
+                match *value {
+                    Value::Object(_) => {
+                        let _ = writeln!(out, "{:width$}{}:", "", key, width = indent);
+                        format_value(out, value, indent + 4);
+                    }
+                    Value::Array(ref items) if items.iter().any(|item| item.is_object()) => {
+                        let _ = writeln!(out, "{:width$}{}:", "", key, width = indent);
+                        for item in items.iter() {
+                            let _ = writeln!(out, "{:width$}-", "", width = indent + 4);
+                            format_value(out, item, indent + 8);
+                        }
+                    }
+                    _ => {
+                        let _ = writeln!(
+                            out,
+                            "{:width$}{}: {}",
+                            "",
+                            key,
+                            scalar_string(value),
+                            width = indent
+                        );
+                    }
+                }
+            }
+        }
+        _ => {
+            let _ = writeln!(out, "{:width$}{}", "", scalar_string(value), width = indent);
+        }
+    }
+}
+
+fn scalar_string(value: &Value) -> String {
+    match *value {
+        Value::String(ref string) => string.clone(),
+        Value::Array(ref items) => items
+            .iter()
+            .map(scalar_string)
+            .collect::<Vec<String>>()
+            .join(", "),
+        ref other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_nested_objects_and_scalar_arrays() {
+        let report = json!({
+            "name": "Fake GPU",
+            "api_version": "1.1.0",
+            "limits": {
+                "max_image_dimension2_d": 16384,
+                "point_size_range": [1.0, 64.0],
+                "sparse": {
+                    "residency_standard2_d_block_shape": true,
+                },
+            },
+            "extensions": ["VK_KHR_swapchain", "VK_KHR_maintenance1"],
+        });
+
+        assert_eq!(
+            format_report(&report),
+            "name: Fake GPU\n\
+             api_version: 1.1.0\n\
+             limits:\n    \
+                 max_image_dimension2_d: 16384\n    \
+                 point_size_range: 1.0, 64.0\n    \
+                 sparse:\n        \
+                     residency_standard2_d_block_shape: true\n\
+             extensions: VK_KHR_swapchain, VK_KHR_maintenance1\n"
+        );
+    }
+
+    #[test]
+    fn formats_arrays_of_objects_as_items() {
+        let report = json!({
+            "queue_families": [
+                { "index": 0, "flags": "GRAPHICS, COMPUTE", "granularity": { "width": 1 } },
+                { "index": 1, "flags": "TRANSFER", "granularity": { "width": 8 } },
+            ],
+            "heaps": [],
+        });
+
+        assert_eq!(
+            format_report(&report),
+            "queue_families:\n    \
+                 -\n        \
+                     index: 0\n        \
+                     flags: GRAPHICS, COMPUTE\n        \
+                     granularity:\n            \
+                         width: 1\n    \
+                 -\n        \
+                     index: 1\n        \
+                     flags: TRANSFER\n        \
+                     granularity:\n            \
+                         width: 8\n\
+             heaps: \n"
+        );
+    }
+
+    #[test]
+    fn formats_top_level_scalars() {
+        assert_eq!(format_report(&json!("Fake GPU")), "Fake GPU\n");
+        assert_eq!(format_report(&json!([1, 2, 3])), "1, 2, 3\n");
+    }
+}
@@ -0,0 +1,303 @@
+// Setup shared by the samples, from the instance up to the logical device.
+//
+// `HeadlessSample` is for samples that never present anything. For the ones that render to a
+// swapchain, `SampleWindow` opens the window, or decides to go headless, and later drives the event
+// and draw loop, while `WindowedSample` also creates the surface and picks its format and present
+// mode. Samples only add what they are about: a depth buffer, a render pass, framebuffers, and what
+// gets recorded each frame.
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::rc::Rc;
+
+use ash::vk;
+use ash::extensions::Swapchain;
+use winit;
+
+use device::{create_ldevice, DeviceRequirements};
+use enumerate_pdevices;
+use error::{Error, Result};
+use frame::{Frame, FrameLoop};
+use owned::{OwnedDevice, OwnedInstance, OwnedSurface};
+use pdevice::{rank_pdevices, DefaultScorer, PdeviceSelector};
+use query::{find_pdevice_with_queue_family_supporting_graphics_and_presentation,
+            find_relevant_pdevice_and_queue_family, InstanceQuery};
+use queues::{QueuePlan, QueueRole};
+use surface::{create_headless_surface, create_window_surface, headless_extension_names,
+              headless_from_args_or_env, SurfaceBackend};
+use swapchain::{PresentModePolicy, SharingPolicy, SurfaceFormatPolicy, SwapchainManager,
+                SwapchainSharing};
+
+// nothing is shown without a window, but a few frames still exercise the whole loop
+pub const HEADLESS_FRAMES: usize = 10;
+
+pub struct HeadlessSample {
+    pub queues: HashMap<QueueRole, vk::Queue>,
+    pub ldevice: Rc<OwnedDevice>,
+    pub queue_plan: QueuePlan,
+    pub pdevice: vk::PhysicalDevice,
+    pub instance: Rc<OwnedInstance>,
+}
+
+impl HeadlessSample {
+    // The best ranked pdevice with a graphics family, after the usual device selector, and a logical
+    // device with a queue for each of `roles`.
+    pub unsafe fn new(
+        instance: &Rc<OwnedInstance>,
+        roles: &[(QueueRole, f32)],
+    ) -> Result<HeadlessSample> {
+        let query = InstanceQuery::new(instance);
+        let pdevices = rank_pdevices(
+            &query,
+            enumerate_pdevices(instance)?,
+            &DefaultScorer::new(),
+            PdeviceSelector::from_args_or_env()?.as_ref(),
+        )?;
+        let (pdevice, _) =
+            find_relevant_pdevice_and_queue_family(&query, pdevices, vec![vk::QUEUE_GRAPHICS_BIT])?;
+
+        let queue_plan = QueuePlan::for_pdevice(instance, pdevice, None, roles)?;
+        let (ldevice, queues) =
+            create_ldevice(instance, pdevice, &queue_plan, &DeviceRequirements::new())?;
+
+        Ok(HeadlessSample {
+            queues,
+            ldevice,
+            queue_plan,
+            pdevice,
+            instance: instance.clone(),
+        })
+    }
+}
+
+pub struct SampleWindow {
+    // `None` when running headless
+    pub window: Option<(winit::EventsLoop, winit::Window)>,
+    pub backend: Option<SurfaceBackend>,
+    pub extent: vk::Extent2D,
+}
+
+impl SampleWindow {
+    // The window comes first: which surface extension the instance needs depends on whether winit
+    // ended up on Wayland or X11. `--headless` skips it. `width` and `height` are in logical pixels.
+    pub fn new(width: u32, height: u32) -> Result<SampleWindow> {
+        let extent = vk::Extent2D { width, height };
+        if headless_from_args_or_env() {
+            return Ok(SampleWindow {
+                window: None,
+                backend: None,
+                extent,
+            });
+        }
+
+        let requested_backend = SurfaceBackend::from_args_or_env()?;
+        if let Some(backend) = requested_backend {
+            backend.request_from_winit();
+        }
+        let events_loop = winit::EventsLoop::new();
+        let window = winit::WindowBuilder::new()
+            .with_title("Tutorial")
+            .with_dimensions(winit::dpi::LogicalSize::from((width, height)))
+            .build(&events_loop)
+            .map_err(Error::Window)?;
+
+        // swapchains are sized in physical pixels, as they are after a resize
+        let extent = match window.get_inner_size() {
+            Some(size) => {
+                let size = size.to_physical(window.get_hidpi_factor());
+                vk::Extent2D {
+                    width: size.width as u32,
+                    height: size.height as u32,
+                }
+            }
+            None => extent,
+        };
+
+        let backend = requested_backend.unwrap_or_else(|| SurfaceBackend::detect(&window));
+        info!("Using the {:?} surface backend.", backend);
+
+        Ok(SampleWindow {
+            window: Some((events_loop, window)),
+            backend: Some(backend),
+            extent,
+        })
+    }
+
+    // instance extensions needed for `create_surface`
+    pub fn extension_names(&self) -> Vec<&'static CStr> {
+        match self.backend {
+            Some(backend) => backend.extension_names(),
+            None => headless_extension_names(),
+        }
+    }
+
+    // A headless surface has no extent of its own, so swapchains fall back to `extent`, just as
+    // they do for windows that let the swapchain decide.
+    pub unsafe fn create_surface(&self, instance: &Rc<OwnedInstance>) -> Result<Rc<OwnedSurface>> {
+        match (&self.window, self.backend) {
+            (&Some((_, ref window)), Some(backend)) => {
+                create_window_surface(instance, window, backend)
+            }
+            _ => create_headless_surface(instance),
+        }
+    }
+
+    // Draws frames with `record` until the window is closed, or `HEADLESS_FRAMES` of them without
+    // a window, then waits for the device to finish them. A frame that `record` fails on is logged
+    // and skipped; any other error ends the loop.
+    pub unsafe fn run_frames<F: FnMut(&Frame) -> Result<()>>(
+        &mut self,
+        swapchain: &mut SwapchainManager,
+        frame_loop: &mut FrameLoop,
+        mut record: F,
+    ) -> Result<()> {
+        match self.window {
+            Some((ref mut events_loop, ref window)) => {
+                let mut running = true;
+                let mut generation = swapchain.generation;
+                while running {
+                    events_loop.poll_events(|event| {
+                        if let winit::Event::WindowEvent {
+                            event: winit::WindowEvent::CloseRequested,
+                            ..
+                        } = event
+                        {
+                            running = false;
+                        }
+                        swapchain.handle_event(&event, window);
+                    });
+                    if !running {
+                        break;
+                    }
+
+                    draw_frame_or_skip(swapchain, frame_loop, &mut record)?;
+
+                    if swapchain.generation != generation {
+                        generation = swapchain.generation;
+                        info!(
+                            "Recreated swapchain at {}x{}.",
+                            swapchain.extent.width, swapchain.extent.height
+                        );
+                    }
+                }
+            }
+            None => {
+                for _ in 0..HEADLESS_FRAMES {
+                    draw_frame_or_skip(swapchain, frame_loop, &mut record)?;
+                }
+                info!("Drew {} frames to the headless surface.", HEADLESS_FRAMES);
+            }
+        }
+
+        // nothing may still be executing once the window, and with it the surface, goes away
+        frame_loop.wait_idle()
+    }
+}
+
+// the frame loop still presents the image of a frame that failed to record, so the loop can go on
+unsafe fn draw_frame_or_skip<F: FnMut(&Frame) -> Result<()>>(
+    swapchain: &mut SwapchainManager,
+    frame_loop: &mut FrameLoop,
+    record: &mut F,
+) -> Result<()> {
+    let mut recording_failed = false;
+    let drawn = frame_loop.draw_frame(swapchain, |frame| {
+        let recorded = record(frame);
+        recording_failed = recorded.is_err();
+        recorded
+    });
+    match drawn {
+        Err(ref error) if recording_failed => {
+            warn!("Skipped a frame: {}", error);
+            Ok(())
+        }
+        drawn => drawn.map(|_| ()),
+    }
+}
+
+pub struct WindowedSample {
+    pub queues: HashMap<QueueRole, vk::Queue>,
+    pub ldevice: Rc<OwnedDevice>,
+    // if one family can do both, the plan uses a single queue for graphics and presentation
+    pub queue_plan: QueuePlan,
+    pub surface_format: vk::SurfaceFormatKHR,
+    pub present_mode: vk::PresentModeKHR,
+    pub pdevice: vk::PhysicalDevice,
+    pub surface: Rc<OwnedSurface>,
+    pub instance: Rc<OwnedInstance>,
+}
+
+impl WindowedSample {
+    // `instance` must have been created with the extensions from `window.extension_names()`. The
+    // pdevice has to support swapchains and have queue families for graphics and presenting to the
+    // surface; the usual device selector, `--present-mode` and surface format policy apply.
+    pub unsafe fn new(
+        window: &SampleWindow,
+        instance: &Rc<OwnedInstance>,
+    ) -> Result<WindowedSample> {
+        let surface = window.create_surface(instance)?;
+
+        let pdevices = rank_pdevices(
+            &InstanceQuery::new(instance),
+            enumerate_pdevices(instance)?,
+            &DefaultScorer::new().require_extension(Swapchain::name()),
+            PdeviceSelector::from_args_or_env()?.as_ref(),
+        )?;
+        let (pdevice, _, _) = find_pdevice_with_queue_family_supporting_graphics_and_presentation(
+            &InstanceQuery::new(instance).with_surface(&surface),
+            pdevices,
+        )?;
+
+        let queue_plan = QueuePlan::for_pdevice(
+            instance,
+            pdevice,
+            Some(&surface),
+            &[(QueueRole::Graphics, 1.0), (QueueRole::Present, 1.0)],
+        )?;
+        let (ldevice, queues) = create_ldevice(
+            instance,
+            pdevice,
+            &queue_plan,
+            &DeviceRequirements::new().require_extension(Swapchain::name()),
+        )?;
+
+        let surface_formats = surface
+            .loader
+            .get_physical_device_surface_formats_khr(pdevice, surface.surface)
+            .map_err(|result| Error::vk("querying surface formats", result))?;
+        let surface_format = SurfaceFormatPolicy::default().choose(&surface_formats)?;
+        info!("Surface format: {}", surface_format);
+
+        let present_modes = surface
+            .loader
+            .get_physical_device_surface_present_modes_khr(pdevice, surface.surface)
+            .map_err(|result| Error::vk("querying surface present modes", result))?;
+        let present_mode = PresentModePolicy::from_args_or_env()?.choose(&present_modes);
+        info!("Present mode: {}", present_mode);
+
+        Ok(WindowedSample {
+            queues,
+            ldevice,
+            queue_plan,
+            surface_format: surface_format.format,
+            present_mode: present_mode.mode,
+            pdevice,
+            surface,
+            instance: instance.clone(),
+        })
+    }
+
+    // When graphics and presentation use different families, the images are shared concurrently
+    // unless `--sharing exclusive` asks for explicit ownership transfers.
+    pub unsafe fn create_swapchain(&self, window: &SampleWindow) -> Result<SwapchainManager> {
+        let sharing = SwapchainSharing::from_plan(&self.queue_plan, SharingPolicy::from_args()?)?;
+        SwapchainManager::new(
+            &self.ldevice,
+            &self.surface,
+            self.surface_format,
+            self.present_mode,
+            window.extent,
+            sharing,
+        )
+    }
+}
@@ -0,0 +1,185 @@
+// Loading SPIR-V and creating shader modules from it.
+//
+// Drivers trust the code they are handed: a truncated or byte-swapped blob tends to crash inside
+// `vkCreateShaderModule` rather than fail it. So the header is checked first, and the code is
+// copied into a `Vec<u32>`, which also takes care of `include_bytes!` data not being 4-byte aligned.
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::ptr;
+use std::rc::Rc;
+use std::result;
+
+use ash::vk;
+use ash::version::DeviceV1_0;
+
+use error::{Error, Result};
+use owned::OwnedDevice;
+
+pub const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+// magic number, version, generator, bound and schema
+const HEADER_WORDS: usize = 5;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpirvProblem {
+    // fewer bytes than the 5-word header needs
+    TooShort(usize),
+    // a byte length that is not a whole number of 32-bit words
+    Misaligned(usize),
+    // the first word is not the SPIR-V magic number in either byte order
+    BadMagic(u32),
+}
+
+impl fmt::Display for SpirvProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SpirvProblem::TooShort(length) => write!(
+                f,
+                "{} bytes is too short for a SPIR-V header of {} bytes",
+                length,
+                HEADER_WORDS * 4
+            ),
+            SpirvProblem::Misaligned(length) => {
+                write!(f, "{} bytes is not a multiple of the 4-byte word size", length)
+            }
+            SpirvProblem::BadMagic(magic) => write!(
+                f,
+                "magic number {:#010x} is not SPIR-V's {:#010x}",
+                magic, SPIRV_MAGIC
+            ),
+        }
+    }
+}
+
+// Checks that `bytes` look like a SPIR-V module and returns its words in host byte order. SPIR-V
+// may be stored in either byte order; the magic number says which one.
+pub fn spirv_words(bytes: &[u8]) -> result::Result<Vec<u32>, SpirvProblem> {
+    if bytes.len() % 4 != 0 {
+        return Err(SpirvProblem::Misaligned(bytes.len()));
+    }
+    if bytes.len() < HEADER_WORDS * 4 {
+        return Err(SpirvProblem::TooShort(bytes.len()));
+    }
+
+    let little_endian = |word: &[u8]| {
+        u32::from(word[0]) | u32::from(word[1]) << 8 | u32::from(word[2]) << 16
+            | u32::from(word[3]) << 24
+    };
+    let words: Vec<u32> = bytes.chunks(4).map(little_endian).collect();
+
+    match words[0] {
+        SPIRV_MAGIC => Ok(words),
+        magic if magic.swap_bytes() == SPIRV_MAGIC => {
+            Ok(words.into_iter().map(u32::swap_bytes).collect())
+        }
+        magic => Err(SpirvProblem::BadMagic(magic)),
+    }
+}
+
+pub struct OwnedShaderModule {
+    pub device: Rc<OwnedDevice>,
+    pub module: vk::ShaderModule,
+    // where the code came from, for error messages and debugging
+    pub name: String,
+}
+
+impl Drop for OwnedShaderModule {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_shader_module(self.module, None);
+        }
+    }
+}
+
+// `name` only shows up in errors, e.g. the path or the `include_bytes!` argument.
+pub unsafe fn create_shader_module(
+    device: &Rc<OwnedDevice>,
+    name: &str,
+    bytes: &[u8],
+) -> Result<OwnedShaderModule> {
+    let words = spirv_words(bytes).map_err(|problem| Error::InvalidSpirv {
+        name: String::from(name),
+        problem,
+    })?;
+
+    let create_info = vk::ShaderModuleCreateInfo {
+        s_type: vk::StructureType::ShaderModuleCreateInfo,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        code_size: words.len() * 4,
+        p_code: words.as_ptr(),
+    };
+
+    match device.create_shader_module(&create_info, None) {
+        Ok(module) => Ok(OwnedShaderModule {
+            device: device.clone(),
+            module,
+            name: String::from(name),
+        }),
+        Err(result) => Err(Error::vk("creating shader module", result)),
+    }
+}
+
+pub unsafe fn load_shader_module<P: AsRef<Path>>(
+    device: &Rc<OwnedDevice>,
+    path: P,
+) -> Result<OwnedShaderModule> {
+    let mut bytes = Vec::new();
+    File::open(path.as_ref())?.read_to_end(&mut bytes)?;
+    create_shader_module(device, &path.as_ref().display().to_string(), &bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(magic: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for &word in [magic, 0x0001_0000, 0, 1, 0].iter() {
+            for shift in [0, 8, 16, 24].iter() {
+                bytes.push((word >> shift) as u8);
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_little_endian_modules() {
+        assert_eq!(
+            spirv_words(&header(SPIRV_MAGIC)),
+            Ok(vec![SPIRV_MAGIC, 0x0001_0000, 0, 1, 0])
+        );
+    }
+
+    #[test]
+    fn swaps_big_endian_modules() {
+        let mut bytes = header(SPIRV_MAGIC);
+        for word in bytes.chunks_mut(4) {
+            word.reverse();
+        }
+        assert_eq!(spirv_words(&bytes), Ok(vec![SPIRV_MAGIC, 0x0001_0000, 0, 1, 0]));
+    }
+
+    #[test]
+    fn rejects_malformed_modules() {
+        assert_eq!(spirv_words(&[]), Err(SpirvProblem::TooShort(0)));
+        assert_eq!(spirv_words(&header(SPIRV_MAGIC)[..16]), Err(SpirvProblem::TooShort(16)));
+
+        let mut bytes = header(SPIRV_MAGIC);
+        bytes.push(0);
+        assert_eq!(spirv_words(&bytes), Err(SpirvProblem::Misaligned(21)));
+
+        assert_eq!(spirv_words(&header(0xdead_beef)), Err(SpirvProblem::BadMagic(0xdead_beef)));
+    }
+
+    #[test]
+    fn accepts_the_bundled_shaders() {
+        let vert = spirv_words(::shaders::DRAW_CUBE_VERT).unwrap();
+        let frag = spirv_words(::shaders::DRAW_CUBE_FRAG).unwrap();
+        assert_eq!(vert[0], SPIRV_MAGIC);
+        assert_eq!(frag[0], SPIRV_MAGIC);
+    }
+}
@@ -0,0 +1,306 @@
+// Surface creation for each window system the samples run on, and for no window system at all.
+//
+// On unix, winit may be running on Wayland or X11; on X11 a surface can be created through either
+// Xlib or XCB. `SurfaceBackend` says which, and picks the matching instance extension.
+//
+// `VK_EXT_headless_surface` gives a surface with no display behind it: the swapchain code paths
+// work as usual, but nothing is ever shown. That is enough to run the swapchain samples on machines
+// without a display, e.g. CI against a software ICD like lavapipe. ash has no loader for the
+// extension, so its single entry point is looked up by hand.
+
+use std::env;
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::rc::Rc;
+
+use ash::vk;
+use ash::extensions::Surface;
+#[cfg(windows)]
+use ash::extensions::Win32Surface;
+#[cfg(all(unix, not(target_os = "android")))]
+use ash::extensions::{WaylandSurface, XcbSurface, XlibSurface};
+use ash::version::{EntryV1_0, InstanceV1_0};
+use winit;
+
+use error::{Error, Result};
+use owned::{OwnedInstance, OwnedSurface};
+
+// set to "xlib", "xcb" or "wayland" to force a surface backend; `--surface-backend` wins over it
+pub const SURFACE_BACKEND_VAR: &str = "ASH_SAMPLES_SURFACE_BACKEND";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SurfaceBackend {
+    Xlib,
+    Xcb,
+    Wayland,
+    Win32,
+}
+
+impl SurfaceBackend {
+    pub fn parse(name: &str) -> Result<SurfaceBackend> {
+        match name.trim().to_lowercase().as_str() {
+            "xlib" => Ok(SurfaceBackend::Xlib),
+            "xcb" => Ok(SurfaceBackend::Xcb),
+            "wayland" => Ok(SurfaceBackend::Wayland),
+            "win32" => Ok(SurfaceBackend::Win32),
+            _ => Err(Error::UnknownSurfaceBackend(String::from(name.trim()))),
+        }
+    }
+
+    // `--surface-backend <name>` or `--surface-backend=<name>`, then `ASH_SAMPLES_SURFACE_BACKEND`;
+    // `None` leaves the choice to `detect`
+    pub fn from_args_or_env() -> Result<Option<SurfaceBackend>> {
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--surface-backend" {
+                if let Some(name) = args.next() {
+                    return SurfaceBackend::parse(&name).map(Some);
+                }
+            } else if arg.starts_with("--surface-backend=") {
+                return SurfaceBackend::parse(&arg["--surface-backend=".len()..]).map(Some);
+            }
+        }
+
+        match env::var(SURFACE_BACKEND_VAR) {
+            Ok(name) => SurfaceBackend::parse(&name).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    // Makes winit open its windows on the display server this backend talks to. Has to be called
+    // before the `winit::EventsLoop` is created.
+    pub fn request_from_winit(&self) {
+        match *self {
+            SurfaceBackend::Xlib | SurfaceBackend::Xcb => env::set_var("WINIT_UNIX_BACKEND", "x11"),
+            SurfaceBackend::Wayland => env::set_var("WINIT_UNIX_BACKEND", "wayland"),
+            SurfaceBackend::Win32 => (),
+        }
+    }
+
+    // The backend matching the display server `window` lives on. On X11 this is Xlib, which is what
+    // winit itself uses.
+    #[cfg(all(unix, not(target_os = "android")))]
+    pub fn detect(window: &winit::Window) -> SurfaceBackend {
+        use winit::os::unix::WindowExt;
+        match window.get_wayland_display() {
+            Some(_) => SurfaceBackend::Wayland,
+            None => SurfaceBackend::Xlib,
+        }
+    }
+
+    #[cfg(windows)]
+    pub fn detect(_window: &winit::Window) -> SurfaceBackend {
+        SurfaceBackend::Win32
+    }
+
+    pub fn extension_name(&self) -> &'static CStr {
+        match *self {
+            SurfaceBackend::Xlib => CStr::from_bytes_with_nul(b"VK_KHR_xlib_surface\0").unwrap(),
+            SurfaceBackend::Xcb => CStr::from_bytes_with_nul(b"VK_KHR_xcb_surface\0").unwrap(),
+            SurfaceBackend::Wayland => {
+                CStr::from_bytes_with_nul(b"VK_KHR_wayland_surface\0").unwrap()
+            }
+            SurfaceBackend::Win32 => CStr::from_bytes_with_nul(b"VK_KHR_win32_surface\0").unwrap(),
+        }
+    }
+
+    // instance extensions needed to present to a window through this backend
+    pub fn extension_names(&self) -> Vec<&'static CStr> {
+        vec![Surface::name(), self.extension_name()]
+    }
+}
+
+// `instance` must have been created with the extensions from `backend.extension_names()`.
+pub unsafe fn create_window_surface(
+    instance: &Rc<OwnedInstance>,
+    window: &winit::Window,
+    backend: SurfaceBackend,
+) -> Result<Rc<OwnedSurface>> {
+    let loader = Surface::new(&instance.entry, &***instance)
+        .map_err(|missing| Error::extension_load(Surface::name(), missing))?;
+    let surface = create_platform_surface(instance, window, backend)?;
+    Ok(OwnedSurface::new(instance, loader, surface))
+}
+
+#[cfg(all(unix, not(target_os = "android")))]
+unsafe fn create_platform_surface(
+    instance: &Rc<OwnedInstance>,
+    window: &winit::Window,
+    backend: SurfaceBackend,
+) -> Result<vk::SurfaceKHR> {
+    use winit::os::unix::WindowExt;
+
+    // winit only hands out the handles for the display server it is actually running on
+    let unavailable = || Error::UnavailableSurfaceBackend(format!("{:?}", backend));
+
+    match backend {
+        SurfaceBackend::Xlib => {
+            let create_info = vk::XlibSurfaceCreateInfoKHR {
+                s_type: vk::StructureType::XlibSurfaceCreateInfoKhr,
+                p_next: ptr::null(),
+                flags: Default::default(),
+                window: window.get_xlib_window().ok_or_else(unavailable)? as vk::Window,
+                dpy: window.get_xlib_display().ok_or_else(unavailable)? as *mut vk::Display,
+            };
+            let loader = XlibSurface::new(&instance.entry, &***instance)
+                .map_err(|missing| Error::extension_load(XlibSurface::name(), missing))?;
+            loader
+                .create_xlib_surface_khr(&create_info, None)
+                .map_err(|result| Error::vk("creating Xlib surface", result))
+        }
+        SurfaceBackend::Xcb => {
+            let create_info = vk::XcbSurfaceCreateInfoKHR {
+                s_type: vk::StructureType::XcbSurfaceCreateInfoKhr,
+                p_next: ptr::null(),
+                flags: Default::default(),
+                // an XCB window is the same X11 resource as the Xlib one
+                window: window.get_xlib_window().ok_or_else(unavailable)? as _,
+                connection: window.get_xcb_connection().ok_or_else(unavailable)? as *mut _,
+            };
+            let loader = XcbSurface::new(&instance.entry, &***instance)
+                .map_err(|missing| Error::extension_load(XcbSurface::name(), missing))?;
+            loader
+                .create_xcb_surface_khr(&create_info, None)
+                .map_err(|result| Error::vk("creating XCB surface", result))
+        }
+        SurfaceBackend::Wayland => {
+            let create_info = vk::WaylandSurfaceCreateInfoKHR {
+                s_type: vk::StructureType::WaylandSurfaceCreateInfoKhr,
+                p_next: ptr::null(),
+                flags: Default::default(),
+                display: window.get_wayland_display().ok_or_else(unavailable)? as *mut _,
+                surface: window.get_wayland_surface().ok_or_else(unavailable)? as *mut _,
+            };
+            let loader = WaylandSurface::new(&instance.entry, &***instance)
+                .map_err(|missing| Error::extension_load(WaylandSurface::name(), missing))?;
+            loader
+                .create_wayland_surface_khr(&create_info, None)
+                .map_err(|result| Error::vk("creating Wayland surface", result))
+        }
+        SurfaceBackend::Win32 => Err(unavailable()),
+    }
+}
+
+#[cfg(windows)]
+unsafe fn create_platform_surface(
+    instance: &Rc<OwnedInstance>,
+    window: &winit::Window,
+    backend: SurfaceBackend,
+) -> Result<vk::SurfaceKHR> {
+    use winapi::shared::windef::HWND;
+    use winapi::um::winuser::GetWindow;
+    use winit::os::windows::WindowExt;
+
+    if backend != SurfaceBackend::Win32 {
+        return Err(Error::UnavailableSurfaceBackend(format!("{:?}", backend)));
+    }
+
+    let hwnd = window.get_hwnd() as HWND;
+    let hinstance = GetWindow(hwnd, 0) as *const vk::c_void;
+    let create_info = vk::Win32SurfaceCreateInfoKHR {
+        s_type: vk::StructureType::Win32SurfaceCreateInfoKhr,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        hinstance: hinstance,
+        hwnd: hwnd as *const vk::c_void,
+    };
+    let loader = Win32Surface::new(&instance.entry, &***instance)
+        .map_err(|missing| Error::extension_load(Win32Surface::name(), missing))?;
+    loader
+        .create_win32_surface_khr(&create_info, None)
+        .map_err(|result| Error::vk("creating Win32 surface", result))
+}
+
+// set to anything but "0" to run the swapchain samples without a window
+pub const HEADLESS_VAR: &str = "ASH_SAMPLES_HEADLESS";
+
+// VK_STRUCTURE_TYPE_HEADLESS_SURFACE_CREATE_INFO_EXT, which `vk::StructureType` does not know about
+const HEADLESS_SURFACE_CREATE_INFO_EXT: u32 = 1000256000;
+
+#[repr(C)]
+struct HeadlessSurfaceCreateInfoEXT {
+    s_type: u32,
+    p_next: *const c_void,
+    flags: vk::Flags,
+}
+
+type CreateHeadlessSurfaceEXT = unsafe extern "system" fn(
+    instance: vk::Instance,
+    p_create_info: *const HeadlessSurfaceCreateInfoEXT,
+    p_allocator: *const vk::AllocationCallbacks,
+    p_surface: *mut vk::SurfaceKHR,
+) -> vk::Result;
+
+// `vkGetInstanceProcAddr` as the loader declares it, returning NULL for entry points it does not
+// know. ash's declaration returns a plain function pointer, which can never be null.
+type GetInstanceProcAddr = unsafe extern "system" fn(
+    instance: vk::Instance,
+    p_name: *const c_char,
+) -> Option<unsafe extern "system" fn()>;
+
+pub fn headless_surface_name() -> &'static CStr {
+    CStr::from_bytes_with_nul(b"VK_EXT_headless_surface\0").unwrap()
+}
+
+// instance extensions needed to create a headless surface
+pub fn headless_extension_names() -> Vec<&'static CStr> {
+    vec![Surface::name(), headless_surface_name()]
+}
+
+// `--headless` on the command line, or `ASH_SAMPLES_HEADLESS` in the environment
+pub fn headless_from_args_or_env() -> bool {
+    env::args().skip(1).any(|arg| arg == "--headless")
+        || env::var(HEADLESS_VAR).map(|value| value != "0").unwrap_or(false)
+}
+
+// `instance` must have been created with the extensions from `headless_extension_names`.
+pub unsafe fn create_headless_surface(instance: &Rc<OwnedInstance>) -> Result<Rc<OwnedSurface>> {
+    let loader = Surface::new(&instance.entry, &***instance)
+        .map_err(|missing| Error::extension_load(Surface::name(), missing))?;
+
+    // Receiving a NULL through ash's declaration would already be undefined behaviour, so the
+    // loader's function is called through one that allows it, and the result only becomes a
+    // `CreateHeadlessSurfaceEXT` once it is known not to be NULL.
+    let get_instance_proc_addr: GetInstanceProcAddr =
+        mem::transmute(instance.entry.static_fn().get_instance_proc_addr);
+    let entry_point = b"vkCreateHeadlessSurfaceEXT\0";
+    let create_headless_surface: CreateHeadlessSurfaceEXT =
+        match get_instance_proc_addr(instance.handle(), entry_point.as_ptr() as *const c_char) {
+            Some(address) => mem::transmute(address),
+            None => {
+                return Err(Error::extension_load(
+                    headless_surface_name(),
+                    vec!["vkCreateHeadlessSurfaceEXT"],
+                ))
+            }
+        };
+
+    let create_info = HeadlessSurfaceCreateInfoEXT {
+        s_type: HEADLESS_SURFACE_CREATE_INFO_EXT,
+        p_next: ptr::null(),
+        flags: 0,
+    };
+    let mut surface = vk::SurfaceKHR::null();
+    match create_headless_surface(instance.handle(), &create_info, ptr::null(), &mut surface) {
+        vk::Result::Success => Ok(OwnedSurface::new(instance, loader, surface)),
+        result => Err(Error::vk("creating headless surface", result)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_surface_backends() {
+        assert_eq!(SurfaceBackend::parse("wayland").unwrap(), SurfaceBackend::Wayland);
+        assert_eq!(SurfaceBackend::parse(" XCB").unwrap(), SurfaceBackend::Xcb);
+
+        match SurfaceBackend::parse("x11") {
+            Err(Error::UnknownSurfaceBackend(name)) => assert_eq!(name, "x11"),
+            other => panic!("expected UnknownSurfaceBackend, got {:?}", other),
+        }
+    }
+}
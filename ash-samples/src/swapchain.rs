@@ -0,0 +1,836 @@
+// Policies for the choices made when creating a swapchain, and `SwapchainManager`, which keeps a
+// swapchain matching its surface as the window is resized.
+
+use std::env;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+
+use ash::vk;
+use ash::extensions::Swapchain;
+use ash::version::DeviceV1_0;
+use winit;
+
+use error::{Error, Result};
+use owned::{OwnedDevice, OwnedSurface, OwnedSwapchain};
+use queues::{QueuePlan, QueueRole};
+
+// An ordered list of acceptable surface formats, best first.
+#[derive(Clone, Debug)]
+pub struct SurfaceFormatPolicy {
+    preferred: Vec<(vk::Format, vk::ColorSpaceKHR)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FormatChoiceReason {
+    // the surface reported a single `UNDEFINED` format, meaning it takes any format we like
+    Unconstrained,
+    // the surface supports the policy's preference at this position
+    Preferred(usize),
+    // the surface supports none of the preferences, so its first reported format was taken
+    Fallback,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceFormatChoice {
+    pub format: vk::SurfaceFormatKHR,
+    pub reason: FormatChoiceReason,
+}
+
+impl SurfaceFormatPolicy {
+    // 8-bit sRGB, so shaders can write linear colors and have them encoded on store
+    pub fn srgb() -> SurfaceFormatPolicy {
+        SurfaceFormatPolicy {
+            preferred: vec![
+                (vk::Format::B8g8r8a8Srgb, vk::ColorSpaceKHR::SrgbNonlinear),
+                (vk::Format::R8g8b8a8Srgb, vk::ColorSpaceKHR::SrgbNonlinear),
+            ],
+        }
+    }
+
+    // 8-bit UNORM, for callers that do their own sRGB encoding
+    pub fn unorm() -> SurfaceFormatPolicy {
+        SurfaceFormatPolicy {
+            preferred: vec![
+                (vk::Format::B8g8r8a8Unorm, vk::ColorSpaceKHR::SrgbNonlinear),
+                (vk::Format::R8g8b8a8Unorm, vk::ColorSpaceKHR::SrgbNonlinear),
+            ],
+        }
+    }
+
+    // exactly `format` in `color_space`, if the surface supports it
+    pub fn exact(format: vk::Format, color_space: vk::ColorSpaceKHR) -> SurfaceFormatPolicy {
+        SurfaceFormatPolicy {
+            preferred: vec![(format, color_space)],
+        }
+    }
+
+    // adds a preference below the existing ones
+    pub fn then(mut self, format: vk::Format, color_space: vk::ColorSpaceKHR) -> SurfaceFormatPolicy {
+        self.preferred.push((format, color_space));
+        self
+    }
+
+    pub fn choose(&self, available: &[vk::SurfaceFormatKHR]) -> Result<SurfaceFormatChoice> {
+        if available.is_empty() {
+            return Err(Error::NoSurfaceFormats);
+        }
+
+        if available.len() == 1 && available[0].format == vk::Format::Undefined {
+            if let Some(&(format, color_space)) = self.preferred.first() {
+                return Ok(SurfaceFormatChoice {
+                    format: vk::SurfaceFormatKHR {
+                        format,
+                        color_space,
+                    },
+                    reason: FormatChoiceReason::Unconstrained,
+                });
+            }
+        }
+
+        for (rank, &(format, color_space)) in self.preferred.iter().enumerate() {
+            let supported = available
+                .iter()
+                .find(|available| available.format == format && available.color_space == color_space);
+            if let Some(supported) = supported {
+                return Ok(SurfaceFormatChoice {
+                    format: supported.clone(),
+                    reason: FormatChoiceReason::Preferred(rank),
+                });
+            }
+        }
+
+        match available.iter().find(|available| available.format != vk::Format::Undefined) {
+            Some(first) => Ok(SurfaceFormatChoice {
+                format: first.clone(),
+                reason: FormatChoiceReason::Fallback,
+            }),
+            None => Err(Error::NoSurfaceFormats),
+        }
+    }
+}
+
+impl Default for SurfaceFormatPolicy {
+    fn default() -> SurfaceFormatPolicy {
+        SurfaceFormatPolicy::srgb()
+    }
+}
+
+impl fmt::Display for SurfaceFormatChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} in {:?}: ",
+            self.format.format, self.format.color_space
+        )?;
+        match self.reason {
+            FormatChoiceReason::Unconstrained => write!(
+                f,
+                "the surface accepts any format, so the first preference was taken"
+            ),
+            FormatChoiceReason::Preferred(0) => write!(f, "the first preference is supported"),
+            FormatChoiceReason::Preferred(rank) => write!(
+                f,
+                "the first {} preference(s) are unsupported, this is the next one",
+                rank
+            ),
+            FormatChoiceReason::Fallback => write!(
+                f,
+                "no preference is supported, so the surface's first format was taken"
+            ),
+        }
+    }
+}
+
+// a comma separated list of present modes, best first; `--present-mode` wins over it
+pub const PRESENT_MODE_VAR: &str = "ASH_SAMPLES_PRESENT_MODE";
+
+// An ordered list of acceptable present modes, best first. FIFO is always supported, so it is the
+// last resort whether or not it is listed.
+#[derive(Clone, Debug)]
+pub struct PresentModePolicy {
+    preferred: Vec<vk::PresentModeKHR>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PresentModeChoice {
+    pub mode: vk::PresentModeKHR,
+    // position in the policy's preferences, or `None` if none was supported
+    pub rank: Option<usize>,
+}
+
+fn parse_present_mode(name: &str) -> Option<vk::PresentModeKHR> {
+    match name.trim().to_lowercase().as_str() {
+        "vsync" | "fifo" => Some(vk::PresentModeKHR::Fifo),
+        "mailbox" => Some(vk::PresentModeKHR::Mailbox),
+        "immediate" => Some(vk::PresentModeKHR::Immediate),
+        "relaxed" | "relaxed-fifo" | "fifo-relaxed" => Some(vk::PresentModeKHR::FifoRelaxed),
+        _ => None,
+    }
+}
+
+impl PresentModePolicy {
+    pub fn new(preferred: Vec<vk::PresentModeKHR>) -> PresentModePolicy {
+        PresentModePolicy { preferred }
+    }
+
+    // e.g. "mailbox,immediate,vsync"; also accepts "relaxed" for FIFO_RELAXED
+    pub fn parse(list: &str) -> Result<PresentModePolicy> {
+        list.split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(|name| {
+                parse_present_mode(name)
+                    .ok_or_else(|| Error::UnknownPresentMode(String::from(name.trim())))
+            })
+            .collect::<Result<Vec<vk::PresentModeKHR>>>()
+            .map(PresentModePolicy::new)
+    }
+
+    // `--present-mode <list>` or `--present-mode=<list>`, then `ASH_SAMPLES_PRESENT_MODE`, then
+    // the default
+    pub fn from_args_or_env() -> Result<PresentModePolicy> {
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--present-mode" {
+                if let Some(list) = args.next() {
+                    return PresentModePolicy::parse(&list);
+                }
+            } else if arg.starts_with("--present-mode=") {
+                return PresentModePolicy::parse(&arg["--present-mode=".len()..]);
+            }
+        }
+
+        match env::var(PRESENT_MODE_VAR) {
+            Ok(list) => PresentModePolicy::parse(&list),
+            Err(_) => Ok(PresentModePolicy::default()),
+        }
+    }
+
+    pub fn choose(&self, available: &[vk::PresentModeKHR]) -> PresentModeChoice {
+        match self.preferred
+            .iter()
+            .position(|preferred| available.contains(preferred))
+        {
+            Some(rank) => PresentModeChoice {
+                mode: self.preferred[rank],
+                rank: Some(rank),
+            },
+            None => PresentModeChoice {
+                mode: vk::PresentModeKHR::Fifo,
+                rank: None,
+            },
+        }
+    }
+}
+
+// mailbox if available, else vsync
+impl Default for PresentModePolicy {
+    fn default() -> PresentModePolicy {
+        PresentModePolicy::new(vec![vk::PresentModeKHR::Mailbox, vk::PresentModeKHR::Fifo])
+    }
+}
+
+impl fmt::Display for PresentModeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.rank {
+            Some(rank) => write!(f, "{:?} (preference {})", self.mode, rank + 1),
+            None => write!(f, "{:?} (no preference is supported)", self.mode),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SharingPolicy {
+    // images are shared between the graphics and present families, with no transfers needed
+    Concurrent,
+    // images belong to one family at a time; see `SwapchainManager::record_release_to_present`
+    Exclusive,
+}
+
+impl SharingPolicy {
+    pub fn parse(name: &str) -> Result<SharingPolicy> {
+        match name.trim().to_lowercase().as_str() {
+            "concurrent" => Ok(SharingPolicy::Concurrent),
+            "exclusive" => Ok(SharingPolicy::Exclusive),
+            _ => Err(Error::UnknownSharingPolicy(String::from(name.trim()))),
+        }
+    }
+
+    // `--sharing <policy>` or `--sharing=<policy>`, `concurrent` by default
+    pub fn from_args() -> Result<SharingPolicy> {
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--sharing" {
+                if let Some(name) = args.next() {
+                    return SharingPolicy::parse(&name);
+                }
+            } else if arg.starts_with("--sharing=") {
+                return SharingPolicy::parse(&arg["--sharing=".len()..]);
+            }
+        }
+        Ok(SharingPolicy::Concurrent)
+    }
+}
+
+// Which queue families use the swapchain images, and how they share them. The policy only matters
+// when graphics and presentation happen on different families; otherwise images are always
+// exclusive to the one family.
+#[derive(Clone, Copy, Debug)]
+pub struct SwapchainSharing {
+    pub graphics_family: u32,
+    pub present_family: u32,
+    pub policy: SharingPolicy,
+}
+
+impl SwapchainSharing {
+    pub fn from_plan(plan: &QueuePlan, policy: SharingPolicy) -> Result<SwapchainSharing> {
+        match (
+            plan.family_index(QueueRole::Graphics),
+            plan.family_index(QueueRole::Present),
+        ) {
+            (Some(graphics_family), Some(present_family)) => Ok(SwapchainSharing {
+                graphics_family,
+                present_family,
+                policy,
+            }),
+            _ => Err(Error::NoQueueFamily {
+                required: String::from("GRAPHICS, PRESENTATION"),
+            }),
+        }
+    }
+
+    fn separate_families(&self) -> bool {
+        self.graphics_family != self.present_family
+    }
+
+    // whether images must be handed from the graphics to the present family with barriers
+    pub fn needs_ownership_transfer(&self) -> bool {
+        self.separate_families() && self.policy == SharingPolicy::Exclusive
+    }
+
+    // the sharing mode and family indices to create the swapchain with
+    fn mode(&self) -> (vk::SharingMode, Vec<u32>) {
+        match self.separate_families() && self.policy == SharingPolicy::Concurrent {
+            true => (
+                vk::SharingMode::Concurrent,
+                vec![self.graphics_family, self.present_family],
+            ),
+            false => (vk::SharingMode::Exclusive, Vec::new()),
+        }
+    }
+}
+
+// Owns the current swapchain and replaces it whenever it stops matching the surface: after the
+// window is resized, or when acquiring or presenting reports `ERROR_OUT_OF_DATE_KHR` or
+// `SUBOPTIMAL_KHR`. Callers check `recreate_if_out_of_date` once per frame, before acquiring.
+pub struct SwapchainManager {
+    pub device: Rc<OwnedDevice>,
+    pub surface: Rc<OwnedSurface>,
+    pub format: vk::SurfaceFormatKHR,
+    pub present_mode: vk::PresentModeKHR,
+    pub sharing: SwapchainSharing,
+    // shared, so framebuffers built on the image views can keep them alive until they are rebuilt
+    pub swapchain: Rc<OwnedSwapchain>,
+    // always includes `COLOR_ATTACHMENT`, plus `TRANSFER_DST` where the surface supports it
+    pub image_usage: vk::ImageUsageFlags,
+    pub extent: vk::Extent2D,
+    // bumped on every recreation, so anything built on the image views knows to rebuild
+    pub generation: u64,
+    // the window's size, for surfaces that leave the extent up to the swapchain
+    window_extent: vk::Extent2D,
+    out_of_date: bool,
+}
+
+impl SwapchainManager {
+    pub unsafe fn new(
+        device: &Rc<OwnedDevice>,
+        surface: &Rc<OwnedSurface>,
+        format: vk::SurfaceFormatKHR,
+        present_mode: vk::PresentModeKHR,
+        window_extent: vk::Extent2D,
+        sharing: SwapchainSharing,
+    ) -> Result<SwapchainManager> {
+        let (swapchain, extent, image_usage) = create_swapchain(
+            device,
+            surface,
+            format,
+            present_mode,
+            window_extent,
+            sharing,
+            vk::SwapchainKHR::null(),
+        )?.ok_or(Error::ZeroSizedSurface)?;
+
+        Ok(SwapchainManager {
+            device: device.clone(),
+            surface: surface.clone(),
+            format,
+            present_mode,
+            sharing,
+            swapchain: Rc::new(swapchain),
+            image_usage,
+            extent,
+            generation: 0,
+            window_extent,
+            out_of_date: false,
+        })
+    }
+
+    pub fn is_out_of_date(&self) -> bool {
+        self.out_of_date
+    }
+
+    pub fn mark_out_of_date(&mut self) {
+        self.out_of_date = true;
+    }
+
+    // Watches for resizes of `window`; pass every event from its events loop.
+    pub fn handle_event(&mut self, event: &winit::Event, window: &winit::Window) {
+        if let winit::Event::WindowEvent {
+            event: winit::WindowEvent::Resized(size),
+            ..
+        } = *event
+        {
+            let size = size.to_physical(window.get_hidpi_factor());
+            self.window_extent = vk::Extent2D {
+                width: size.width as u32,
+                height: size.height as u32,
+            };
+            self.out_of_date = true;
+        }
+    }
+
+    // `None` if the swapchain turned out to be out of date, in which case nothing was acquired and
+    // the frame should be skipped. A suboptimal swapchain still hands out an image that can be
+    // rendered and presented; it is only marked out of date, to be replaced after that frame.
+    pub unsafe fn acquire_next_image(
+        &mut self,
+        semaphore: vk::Semaphore,
+        fence: vk::Fence,
+    ) -> Result<Option<u32>> {
+        // ash's wrapper reports SUBOPTIMAL_KHR as an error and drops the image index with it, and
+        // some platforms report it for every frame of a resize
+        let mut index = 0;
+        let result = self.swapchain.loader.fp().acquire_next_image_khr(
+            self.device.handle(),
+            self.swapchain.swapchain,
+            u64::max_value(),
+            semaphore,
+            fence,
+            &mut index,
+        );
+        match result {
+            vk::Result::Success => Ok(Some(index)),
+            vk::Result::SuboptimalKhr => {
+                self.out_of_date = true;
+                Ok(Some(index))
+            }
+            vk::Result::ErrorOutOfDateKhr => {
+                self.out_of_date = true;
+                Ok(None)
+            }
+            result => Err(Error::vk("acquiring swapchain image", result)),
+        }
+    }
+
+    pub unsafe fn present(
+        &mut self,
+        queue: vk::Queue,
+        image_index: u32,
+        wait_semaphores: &[vk::Semaphore],
+    ) -> Result<()> {
+        let present_info = vk::PresentInfoKHR {
+            s_type: vk::StructureType::PresentInfoKhr,
+            p_next: ptr::null(),
+            wait_semaphore_count: wait_semaphores.len() as u32,
+            p_wait_semaphores: wait_semaphores.as_ptr(),
+            swapchain_count: 1,
+            p_swapchains: &self.swapchain.swapchain,
+            p_image_indices: &image_index,
+            p_results: ptr::null_mut(),
+        };
+
+        match self.swapchain.loader.queue_present_khr(queue, &present_info) {
+            Ok(()) => Ok(()),
+            Err(vk::Result::ErrorOutOfDateKhr) | Err(vk::Result::SuboptimalKhr) => {
+                self.out_of_date = true;
+                Ok(())
+            }
+            Err(result) => Err(Error::vk("presenting swapchain image", result)),
+        }
+    }
+
+    // Returns whether the swapchain was replaced. While the window is minimized the surface has no
+    // area and nothing can be created, so it stays out of date until the window is restored.
+    pub unsafe fn recreate_if_out_of_date(&mut self) -> Result<bool> {
+        if !self.out_of_date {
+            return Ok(false);
+        }
+
+        // the old swapchain's images may still be in use by submitted work
+        self.device
+            .device_wait_idle()
+            .map_err(|result| Error::vk("waiting for device idle", result))?;
+
+        let replacement = create_swapchain(
+            &self.device,
+            &self.surface,
+            self.format,
+            self.present_mode,
+            self.window_extent,
+            self.sharing,
+            self.swapchain.swapchain,
+        )?;
+        let (swapchain, extent, image_usage) = match replacement {
+            Some(replacement) => replacement,
+            None => return Ok(false),
+        };
+
+        // the old swapchain was retired by passing it as `old_swapchain`; its views and then the
+        // swapchain itself are destroyed once nothing else holds on to it
+        drop(mem::replace(&mut self.swapchain, Rc::new(swapchain)));
+        self.extent = extent;
+        self.image_usage = image_usage;
+        self.generation += 1;
+        self.out_of_date = false;
+        Ok(true)
+    }
+
+    // With exclusive sharing across two families, an image rendered on the graphics queue has to be
+    // released by it and acquired by the present queue before it is presented. Record this at the
+    // end of the graphics command buffer, once image `image_index` is in `PRESENT_SRC_KHR`; it does
+    // nothing when no transfer is needed. Nothing has to be transferred back: each frame starts
+    // from an undefined layout, discarding the previous contents.
+    pub unsafe fn record_release_to_present(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image_index: u32,
+    ) {
+        if self.sharing.needs_ownership_transfer() {
+            self.record_ownership_transfer(
+                command_buffer,
+                image_index,
+                vk::ACCESS_COLOR_ATTACHMENT_WRITE_BIT,
+                vk::PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
+            );
+        }
+    }
+
+    // The matching acquire, recorded in a command buffer submitted to the present queue, which has
+    // to wait for the graphics submission (e.g. on a semaphore) and be waited on by the present.
+    pub unsafe fn record_acquire_for_present(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image_index: u32,
+    ) {
+        if self.sharing.needs_ownership_transfer() {
+            self.record_ownership_transfer(
+                command_buffer,
+                image_index,
+                vk::AccessFlags::empty(),
+                vk::PIPELINE_STAGE_TOP_OF_PIPE_BIT,
+            );
+        }
+    }
+
+    // Release and acquire are the same barrier, recorded once on each queue; the access mask and
+    // stage only apply on the releasing side.
+    unsafe fn record_ownership_transfer(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image_index: u32,
+        src_access_mask: vk::AccessFlags,
+        src_stage_mask: vk::PipelineStageFlags,
+    ) {
+        let barrier = vk::ImageMemoryBarrier {
+            s_type: vk::StructureType::ImageMemoryBarrier,
+            p_next: ptr::null(),
+            src_access_mask,
+            dst_access_mask: vk::AccessFlags::empty(),
+            // a pure ownership transfer: the image is already in its presentable layout
+            old_layout: vk::ImageLayout::PresentSrcKhr,
+            new_layout: vk::ImageLayout::PresentSrcKhr,
+            src_queue_family_index: self.sharing.graphics_family,
+            dst_queue_family_index: self.sharing.present_family,
+            image: self.swapchain.images[image_index as usize],
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::IMAGE_ASPECT_COLOR_BIT,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+        };
+        self.device.cmd_pipeline_barrier(
+            command_buffer,
+            src_stage_mask,
+            vk::PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier],
+        );
+    }
+}
+
+// Creates a swapchain and a view of each of its images, or returns `None` if the surface currently
+// has no area.
+unsafe fn create_swapchain(
+    device: &Rc<OwnedDevice>,
+    surface: &Rc<OwnedSurface>,
+    format: vk::SurfaceFormatKHR,
+    present_mode: vk::PresentModeKHR,
+    window_extent: vk::Extent2D,
+    sharing: SwapchainSharing,
+    old_swapchain: vk::SwapchainKHR,
+) -> Result<Option<(OwnedSwapchain, vk::Extent2D, vk::ImageUsageFlags)>> {
+    let capabilities = surface
+        .loader
+        .get_physical_device_surface_capabilities_khr(device.pdevice, surface.surface)
+        .map_err(|result| Error::vk("querying surface capabilities", result))?;
+
+    let extent = match capabilities.current_extent.width {
+        // the surface size is determined by the swapchain extent
+        ::std::u32::MAX => vk::Extent2D {
+            width: window_extent
+                .width
+                .max(capabilities.min_image_extent.width)
+                .min(capabilities.max_image_extent.width),
+            height: window_extent
+                .height
+                .max(capabilities.min_image_extent.height)
+                .min(capabilities.max_image_extent.height),
+        },
+        _ => capabilities.current_extent,
+    };
+    if extent.width == 0 || extent.height == 0 {
+        return Ok(None);
+    }
+
+    let mut desired_image_count = capabilities.min_image_count + 1;
+    // if max_image_count == 0, then there is no software upper limit
+    if capabilities.max_image_count > 0 && desired_image_count > capabilities.max_image_count {
+        desired_image_count = capabilities.max_image_count;
+    }
+
+    let pre_transform = if capabilities
+        .supported_transforms
+        .subset(vk::SURFACE_TRANSFORM_IDENTITY_BIT_KHR)
+    {
+        vk::SURFACE_TRANSFORM_IDENTITY_BIT_KHR
+    } else {
+        capabilities.current_transform
+    };
+
+    // transfers allow clearing or blitting into the images without a render pass
+    let image_usage = vk::IMAGE_USAGE_COLOR_ATTACHMENT_BIT
+        | (capabilities.supported_usage_flags & vk::IMAGE_USAGE_TRANSFER_DST_BIT);
+
+    let loader = Swapchain::new(&**device.instance, &***device)
+        .map_err(|missing| Error::extension_load(Swapchain::name(), missing))?;
+
+    // `queue_family_indices` has to outlive the `create_swapchain_khr` call
+    let (image_sharing_mode, queue_family_indices) = sharing.mode();
+    let create_info = vk::SwapchainCreateInfoKHR {
+        s_type: vk::StructureType::SwapchainCreateInfoKhr,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        surface: surface.surface,
+        min_image_count: desired_image_count,
+        image_color_space: format.color_space,
+        image_format: format.format,
+        image_extent: extent,
+        image_usage,
+        image_sharing_mode,
+        pre_transform,
+        composite_alpha: vk::COMPOSITE_ALPHA_OPAQUE_BIT_KHR,
+        present_mode,
+        clipped: 1,
+        old_swapchain,
+        image_array_layers: 1,
+        p_queue_family_indices: queue_family_indices.as_ptr(),
+        queue_family_index_count: queue_family_indices.len() as u32,
+    };
+
+    let mut swapchain = match loader.create_swapchain_khr(&create_info, None) {
+        Ok(swapchain) => OwnedSwapchain::new(device, surface, loader, swapchain),
+        Err(result) => return Err(Error::vk("creating swapchain", result)),
+    };
+
+    swapchain.images = swapchain
+        .loader
+        .get_swapchain_images_khr(swapchain.swapchain)
+        .map_err(|result| Error::vk("getting swapchain images", result))?;
+
+    // views are pushed one at a time, so the ones already created are destroyed along with the
+    // swapchain if a later one fails
+    for image in swapchain.images.clone() {
+        let create_view_info = vk::ImageViewCreateInfo {
+            s_type: vk::StructureType::ImageViewCreateInfo,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            view_type: vk::ImageViewType::Type2d,
+            format: format.format,
+            components: vk::ComponentMapping {
+                r: vk::ComponentSwizzle::R,
+                g: vk::ComponentSwizzle::G,
+                b: vk::ComponentSwizzle::B,
+                a: vk::ComponentSwizzle::A,
+            },
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::IMAGE_ASPECT_COLOR_BIT,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image,
+        };
+        let view = device
+            .create_image_view(&create_view_info, None)
+            .map_err(|result| Error::vk("creating swapchain image view", result))?;
+        swapchain.image_views.push(view);
+    }
+
+    Ok(Some((swapchain, extent, image_usage)))
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use super::*;
+
+    fn format(format: vk::Format, color_space: vk::ColorSpaceKHR) -> vk::SurfaceFormatKHR {
+        vk::SurfaceFormatKHR {
+            format,
+            color_space,
+        }
+    }
+
+    #[test]
+    fn prefers_srgb_by_default() {
+        let available = [
+            format(vk::Format::B8g8r8a8Unorm, vk::ColorSpaceKHR::SrgbNonlinear),
+            format(vk::Format::B8g8r8a8Srgb, vk::ColorSpaceKHR::SrgbNonlinear),
+        ];
+
+        let choice = SurfaceFormatPolicy::default().choose(&available).unwrap();
+        assert_eq!(choice.format.format, vk::Format::B8g8r8a8Srgb);
+        assert_eq!(choice.reason, FormatChoiceReason::Preferred(0));
+    }
+
+    #[test]
+    fn falls_through_preferences_in_order() {
+        let available = [
+            format(vk::Format::B8g8r8a8Unorm, vk::ColorSpaceKHR::SrgbNonlinear),
+            format(vk::Format::R8g8b8a8Srgb, vk::ColorSpaceKHR::SrgbNonlinear),
+        ];
+
+        let choice = SurfaceFormatPolicy::srgb().choose(&available).unwrap();
+        assert_eq!(choice.format.format, vk::Format::R8g8b8a8Srgb);
+        assert_eq!(choice.reason, FormatChoiceReason::Preferred(1));
+
+        let choice = SurfaceFormatPolicy::unorm().choose(&available).unwrap();
+        assert_eq!(choice.format.format, vk::Format::B8g8r8a8Unorm);
+        assert_eq!(choice.reason, FormatChoiceReason::Preferred(0));
+    }
+
+    #[test]
+    fn undefined_means_any_format() {
+        let available = [format(vk::Format::Undefined, vk::ColorSpaceKHR::SrgbNonlinear)];
+
+        let choice = SurfaceFormatPolicy::srgb().choose(&available).unwrap();
+        assert_eq!(choice.format.format, vk::Format::B8g8r8a8Srgb);
+        assert_eq!(choice.format.color_space, vk::ColorSpaceKHR::SrgbNonlinear);
+        assert_eq!(choice.reason, FormatChoiceReason::Unconstrained);
+    }
+
+    #[test]
+    fn falls_back_to_first_reported_format() {
+        let available = [
+            format(vk::Format::A2b10g10r10UnormPack32, vk::ColorSpaceKHR::SrgbNonlinear),
+            format(vk::Format::B8g8r8a8Unorm, vk::ColorSpaceKHR::SrgbNonlinear),
+        ];
+
+        let choice = SurfaceFormatPolicy::exact(vk::Format::R8g8b8a8Srgb, vk::ColorSpaceKHR::SrgbNonlinear)
+            .choose(&available)
+            .unwrap();
+        assert_eq!(choice.format.format, vk::Format::A2b10g10r10UnormPack32);
+        assert_eq!(choice.reason, FormatChoiceReason::Fallback);
+
+        match SurfaceFormatPolicy::srgb().choose(&[]) {
+            Err(Error::NoSurfaceFormats) => (),
+            other => panic!("expected NoSurfaceFormats, got {:?}", other.map(|c| c.reason)),
+        }
+    }
+
+    #[test]
+    fn parses_present_mode_lists() {
+        let policy = PresentModePolicy::parse("mailbox, Immediate,relaxed,vsync").unwrap();
+        assert_eq!(
+            policy.preferred,
+            vec![
+                vk::PresentModeKHR::Mailbox,
+                vk::PresentModeKHR::Immediate,
+                vk::PresentModeKHR::FifoRelaxed,
+                vk::PresentModeKHR::Fifo,
+            ]
+        );
+
+        match PresentModePolicy::parse("mailbox,tearing") {
+            Err(Error::UnknownPresentMode(name)) => assert_eq!(name, "tearing"),
+            other => panic!("expected UnknownPresentMode, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn falls_back_along_present_mode_list() {
+        let policy = PresentModePolicy::parse("immediate,mailbox").unwrap();
+
+        let choice = policy.choose(&[vk::PresentModeKHR::Fifo, vk::PresentModeKHR::Mailbox]);
+        assert_eq!(choice.mode, vk::PresentModeKHR::Mailbox);
+        assert_eq!(choice.rank, Some(1));
+
+        let choice = policy.choose(&[vk::PresentModeKHR::Fifo]);
+        assert_eq!(choice.mode, vk::PresentModeKHR::Fifo);
+        assert_eq!(choice.rank, None);
+    }
+
+    #[test]
+    fn parses_sharing_policies() {
+        assert_eq!(SharingPolicy::parse("concurrent").unwrap(), SharingPolicy::Concurrent);
+        assert_eq!(SharingPolicy::parse(" Exclusive").unwrap(), SharingPolicy::Exclusive);
+
+        match SharingPolicy::parse("exlusive") {
+            Err(Error::UnknownSharingPolicy(name)) => assert_eq!(name, "exlusive"),
+            other => panic!("expected UnknownSharingPolicy, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sharing_mode_follows_families_and_policy() {
+        let same = SwapchainSharing {
+            graphics_family: 0,
+            present_family: 0,
+            policy: SharingPolicy::Concurrent,
+        };
+        assert_eq!(same.mode(), (vk::SharingMode::Exclusive, vec![]));
+        assert!(!same.needs_ownership_transfer());
+
+        let concurrent = SwapchainSharing {
+            graphics_family: 0,
+            present_family: 2,
+            policy: SharingPolicy::Concurrent,
+        };
+        assert_eq!(concurrent.mode(), (vk::SharingMode::Concurrent, vec![0, 2]));
+        assert!(!concurrent.needs_ownership_transfer());
+
+        let exclusive = SwapchainSharing {
+            policy: SharingPolicy::Exclusive,
+            ..concurrent
+        };
+        assert_eq!(exclusive.mode(), (vk::SharingMode::Exclusive, vec![]));
+        assert!(exclusive.needs_ownership_transfer());
+    }
+}
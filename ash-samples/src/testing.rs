@@ -0,0 +1,108 @@
+// Fixtures shared by the unit tests, and an in-memory `PdeviceQuery` for testing selection logic
+// without a GPU.
+
+use std::ffi::CString;
+use std::mem;
+use std::os::raw::c_char;
+
+use ash::vk;
+
+use error::Result;
+use features;
+use query::PdeviceQuery;
+
+pub fn family(queue_flags: vk::QueueFlags, queue_count: u32) -> vk::QueueFamilyProperties {
+    vk::QueueFamilyProperties {
+        queue_flags,
+        queue_count,
+        timestamp_valid_bits: 64,
+        min_image_transfer_granularity: vk::Extent3D {
+            width: 1,
+            height: 1,
+            depth: 1,
+        },
+    }
+}
+
+pub fn properties(name: &str, vendor_id: u32, device_id: u32) -> vk::PhysicalDeviceProperties {
+    // plain old data; only the fields set below are looked at
+    let mut properties: vk::PhysicalDeviceProperties = unsafe { mem::zeroed() };
+    for (dst, &byte) in properties.device_name.iter_mut().zip(name.as_bytes()) {
+        *dst = byte as c_char;
+    }
+    properties.vendor_id = vendor_id;
+    properties.device_id = device_id;
+    properties
+}
+
+pub struct FakePdevice {
+    pub properties: vk::PhysicalDeviceProperties,
+    pub features: vk::PhysicalDeviceFeatures,
+    pub memory_properties: vk::PhysicalDeviceMemoryProperties,
+    pub extensions: Vec<CString>,
+    pub queue_families: Vec<vk::QueueFamilyProperties>,
+    // one entry per queue family; missing entries count as no support
+    pub present_support: Vec<bool>,
+}
+
+impl FakePdevice {
+    // an unnamed pdevice with no features, extensions or memory, for tests to fill in
+    pub fn new(
+        queue_families: Vec<vk::QueueFamilyProperties>,
+        present_support: Vec<bool>,
+    ) -> FakePdevice {
+        FakePdevice {
+            properties: properties("", 0, 0),
+            features: features::no_features(),
+            // plain old data, like the properties
+            memory_properties: unsafe { mem::zeroed() },
+            extensions: Vec::new(),
+            queue_families,
+            present_support,
+        }
+    }
+}
+
+// In-memory pdevices, addressed by their index in `pdevices`.
+pub struct FakeQuery {
+    pub pdevices: Vec<FakePdevice>,
+}
+
+impl FakeQuery {
+    // the handles to pass to the selection functions: one per fake pdevice
+    pub fn handles(&self) -> Vec<usize> {
+        (0..self.pdevices.len()).collect()
+    }
+}
+
+impl PdeviceQuery for FakeQuery {
+    type Pdevice = usize;
+
+    fn queue_family_properties(&self, pdevice: usize) -> Vec<vk::QueueFamilyProperties> {
+        self.pdevices[pdevice].queue_families.clone()
+    }
+
+    fn surface_support(&self, pdevice: usize, family_index: u32) -> bool {
+        self.pdevices[pdevice]
+            .present_support
+            .get(family_index as usize)
+            .cloned()
+            .unwrap_or(false)
+    }
+
+    fn properties(&self, pdevice: usize) -> vk::PhysicalDeviceProperties {
+        self.pdevices[pdevice].properties.clone()
+    }
+
+    fn features(&self, pdevice: usize) -> vk::PhysicalDeviceFeatures {
+        self.pdevices[pdevice].features.clone()
+    }
+
+    fn memory_properties(&self, pdevice: usize) -> vk::PhysicalDeviceMemoryProperties {
+        self.pdevices[pdevice].memory_properties.clone()
+    }
+
+    fn device_extensions(&self, pdevice: usize) -> Result<Vec<CString>> {
+        Ok(self.pdevices[pdevice].extensions.clone())
+    }
+}